@@ -6,6 +6,10 @@ rule nuke:
 	shell:
 		f"rm -rf gurobi.log runs .err .out .snakemake __pycache__"
 
+# compressed linear programs are written directly if compress_lp is set
+ruleorder: write_founder_flow_lp > compress_flow
+ruleorder: write_minimization_lp > compress_min
+
 rule compress_flow:
 	input:
 		f"{OUTDIR}/{{pset}}/{{sample}}.flow.lp",
@@ -6,6 +6,13 @@ else:
 	os.environ["RUST_LOG"] = "info"
 	os.environ["RUST_BACKTRACE"] = "0"
 XHAPEXP = config.get("xhap_regex", ".")
+# write linear programs gzip-compressed, gurobi reads them directly
+LPCOMPRESS = config.get("compress_lp", False)
+LPEXT = "lp.gz" if LPCOMPRESS else "lp"
+LPOPTS = "--compress gzip" if LPCOMPRESS else ""
+# additional selection of haplotype paths by PanSN name components (sample#hap#contig)
+XHAPOPTS = " ".join(f"-{o} {config[k]}" for o, k in (("s", "xhap_samples"),
+	("H", "xhap_haplotype"), ("c", "xhap_contig")) if k in config)
 
 BASEDIR = workflow.basedir
 OUTDIR = os.path.join(BASEDIR, config.get("outdir", "."))
@@ -16,6 +23,17 @@ BINDIR = os.path.join(REPODIR, "bin")
 RUSTBIN = os.path.join(REPODIR, "target", "debug" if DEBUG else "release")
 ENVDIR = os.path.join(REPODIR, "env")
 
+import re
+
+# graphs of windows (see window.smk) are extracted into the output directory
+def is_window(sample):
+	return re.fullmatch(r".+\.win\d+", sample) is not None
+
+def input_graph(wc):
+	if is_window(wc.sample):
+		return f"{OUTDIR}/windows/{wc.sample}.gfa"
+	return f"{DATADIR}/{wc.sample}.gfa"
+
 envvars:
 	"RUST_LOG",
 	"RUST_BACKTRACE",
@@ -26,6 +44,8 @@ wildcard_constraints:
 
 include: "gurobi.smk"
 include: "aux.smk"
+include: "pareto.smk"
+include: "window.smk"
 
 rule all:
 	input:
@@ -37,7 +57,7 @@ rule all:
 
 rule check_input:
         input:
-                f"{DATADIR}/{{sample}}.gfa"
+                input_graph
         output:
                 f"{OUTDIR}/.{{sample}}.ok"
         shell:
@@ -48,21 +68,25 @@ rule check_input:
 
 rule extract_haplotypes:
 	input:
-		g = f"{DATADIR}/{{sample}}.gfa",
+		g = input_graph,
                 o = f"{OUTDIR}/.{{sample}}.ok"
 	output:
 		f"{OUTDIR}/{{sample}}.haplotypes.txt"
+	params:
+		# paths of windows have been selected when extracting them
+		opts = lambda wc: "" if is_window(wc.sample) else XHAPOPTS
 	shell:
 		f"{SHDIR}/xhap.sh "
+		f"	{{params.opts}}"
 		f"	-p {XHAPEXP}"
 		f"	{{input.g}}"
 		f"	>{{output}}"
 
 rule write_founder_flow_lp:
 	input:
-		f"{DATADIR}/{{sample}}.gfa"
+		input_graph
 	output:
-		f"{OUTDIR}/{{nf}}/{{sample}}.flow.lp"
+		f"{OUTDIR}/{{nf}}/{{sample}}.flow.{LPEXT}"
 	log:
 		f"{OUTDIR}/{{nf}}/log/{{sample}}.flow.lp.log"
 	benchmark:
@@ -70,11 +94,11 @@ rule write_founder_flow_lp:
 	params:
 		nf = lambda wc: f"-f {wc.nf}" if wc.nf != "" else ""
 	shell:
-		f"{RUSTBIN}/mkflow {{params}} {{input}} >{{output}} 2>{{log}}"
+		f"{RUSTBIN}/mkflow {LPOPTS} {{params}} {{input}} >{{output}} 2>{{log}}"
 
 rule solve_founder_flow:
 	input:
-		f"{OUTDIR}/{{pset}}/{{sample}}.flow.lp"
+		f"{OUTDIR}/{{pset}}/{{sample}}.flow.{LPEXT}"
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.flow.sol"
 	log:
@@ -96,9 +120,9 @@ rule solve_founder_flow:
 		f"	Threads={{params.t}}"
 		f"	{{input}} >/dev/null"
 
-rule construct_founder_seqs:
+checkpoint construct_founder_seqs:
 	input:
-		g = f"{DATADIR}/{{sample}}.gfa",
+		g = input_graph,
 		s = f"{OUTDIR}/{{pset}}/{{sample}}.flow.sol",
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.flow.founders.txt"
@@ -108,9 +132,10 @@ rule construct_founder_seqs:
 		f"{OUTDIR}/{{pset}}/log/{{sample}}.flow.founders.prof"
 	shell:
 		f"{RUSTBIN}/flow2seq"
+		f"	-g {{input.g}}"
 		f"	{{input.s}}"
 		f"	>{{output}} 2>{{log}}"
-		f"; {SHDIR}/check_founder_solution.py"
+		f"; {RUSTBIN}/check_founders"
 		f"	{{input.g}}"
 		f"	{{output}} >>{{log}} 2>&1"
 
@@ -126,6 +151,7 @@ rule get_recombination_number:
 	input:
 		f = f"{OUTDIR}/{{pset}}/{{sample}}.flow.founders.txt",
 		h = f"{OUTDIR}/{{sample}}.haplotypes.txt",
+		g = input_graph,
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.flow.nrecomb.txt"
 	log:
@@ -137,6 +163,7 @@ rule get_recombination_number:
 	shell:
 		f"{RUSTBIN}/min_random"
 		f"	-n {{params.ntrials}}"
+		f"	-g {{input.g}}"
 		f"	{{input.f}}"
 		f"	{{input.h}}"
 		f"	>{{output}} 2>{{log}}"
@@ -146,21 +173,23 @@ rule write_minimization_lp:
 		f = f"{OUTDIR}/{{pset}}/{{sample}}.flow.founders.txt",
 		h = f"{OUTDIR}/{{sample}}.haplotypes.txt",
 		r = f"{OUTDIR}/{{pset}}/{{sample}}.flow.nrecomb.txt",
+		g = input_graph,
 	output:
-		f"{OUTDIR}/{{pset}}/{{sample}}.min.lp"
+		f"{OUTDIR}/{{pset}}/{{sample}}.min.{LPEXT}"
 	log:
 		f"{OUTDIR}/{{pset}}/log/{{sample}}.min.lp.log"
 	benchmark:
 		f"{OUTDIR}/{{pset}}/log/{{sample}}.min.lp.prof"
 	shell:
-		f"{RUSTBIN}/mkmin"
+		f"{RUSTBIN}/mkmin {LPOPTS}"
+		f"	-g {{input.g}}"
 		f"	{{input.f}}"
 		f"	{{input.h}}"
 		f"	>{{output}} 2>{{log}}"
 
 rule solve_minimization:
 	input:
-		f"{OUTDIR}/{{pset}}/{{sample}}.min.lp"
+		f"{OUTDIR}/{{pset}}/{{sample}}.min.{LPEXT}"
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.min.sol"
 	log:
@@ -186,39 +215,69 @@ rule solve_minimization:
 rule construct_minimal_founders_output_compact:
 	input:
 		s = f"{OUTDIR}/{{pset}}/{{sample}}.min.sol",
-		g = f"{DATADIR}/{{sample}}.gfa",
+		g = input_graph,
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.compact.txt"
 	log:
 		f"{OUTDIR}/{{pset}}/log/{{sample}}.min.founders.compact.log"
+	params:
+		l = lambda wc: f"{OUTDIR}/{wc.pset}/log/{wc.sample}.min.sol.log"
 	shell:
 		f"{RUSTBIN}/min2seq -c"
+		f"	-L {{params.l}}"
+		f"	-g {{input.g}}"
 		f"	{{input.s}}"
 		f"	>{{output}} 2>{{log}}"
 
 rule construct_minimal_founders_output_long:
 	input:
 		s = f"{OUTDIR}/{{pset}}/{{sample}}.min.sol",
-		g = f"{DATADIR}/{{sample}}.gfa",
+		g = input_graph,
+		h = f"{OUTDIR}/{{sample}}.haplotypes.txt",
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.long.txt"
 	log:
 		f"{OUTDIR}/{{pset}}/log/{{sample}}.min.founders.long.log"
+	params:
+		l = lambda wc: f"{OUTDIR}/{wc.pset}/log/{wc.sample}.min.sol.log"
 	shell:
 		f"{RUSTBIN}/min2seq -l"
+		f"	-L {{params.l}}"
+		f"	--names {{input.h}}"
+		f"	-g {{input.g}}"
 		f"	{{input.s}}"
 		f"	>{{output}} 2>{{log}}"
 
+rule draw_minimal_founders:
+	input:
+		l = f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.long.txt",
+		g = input_graph,
+	output:
+		f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.svg"
+	log:
+		f"{OUTDIR}/{{pset}}/log/{{sample}}.min.founders.svg.log"
+	shell:
+		f"{RUSTBIN}/mosaic2svg"
+		f"	-g {{input.g}}"
+		f"	{{input.l}}"
+		f"	>{{output}} 2>{{log}}"
+
 rule construct_minimal_founders_output_wide:
 	input:
 		s = f"{OUTDIR}/{{pset}}/{{sample}}.min.sol",
-		g = f"{DATADIR}/{{sample}}.gfa",
+		g = input_graph,
+		h = f"{OUTDIR}/{{sample}}.haplotypes.txt",
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.txt"
 	log:
 		f"{OUTDIR}/{{pset}}/log/{{sample}}.min.founders.log"
+	params:
+		l = lambda wc: f"{OUTDIR}/{wc.pset}/log/{wc.sample}.min.sol.log"
 	shell:
 		f"{RUSTBIN}/min2seq"
+		f"	-L {{params.l}}"
+		f"	--names {{input.h}}"
+		f"	-g {{input.g}}"
 		f"	{{input.s}}"
 		f"	>{{output}} 2>{{log}}"
 
@@ -234,14 +293,14 @@ rule construct_minimal_founders_output_gfa:
 
 rule construct_minimal_founders_output_full_gfa:
 	input:
-		h = f"{DATADIR}/{{sample}}.gfa",
+		h = input_graph,
 		m = f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.gfa",
 		c = f"{OUTDIR}/{{pset}}/{{sample}}.min.founders.compact.txt",
 		f = f"{OUTDIR}/{{pset}}/{{sample}}.flow.founders.txt",
 	output:
 		f"{OUTDIR}/{{pset}}/{{sample}}.min.full.gfa",
 	shell:
-		f"cp {{input.h}} {{output}}"
+		f"{SHDIR}/unpack.sh {{input.h}} >{{output}}"
 		f"; {SHDIR}/walk2path.sh {{input.f}}"
 		f"	| sed -n 's/^P\t/&flow_/p' >>{{output}}"
 		f"; sed -n 's/^P\t/#&min_/p' {{input.m}} >>{{output}}"
@@ -0,0 +1,33 @@
+PARETO_STEPS = config.get("pareto_steps", 5)
+
+# founder counts to evaluate, starting at the size of the minimum (unforced) founder set
+def pareto_points(wc):
+	f = checkpoints.construct_founder_seqs.get(pset="0", sample=wc.sample).output[0]
+	with open(f) as data:
+		nmin = sum(1 for line in data if line.strip())
+	return list(range(nmin, nmin + PARETO_STEPS + 1))
+
+rule pareto_front:
+	input:
+		s = lambda wc: expand(f"{OUTDIR}/{{nf}}/{wc.sample}.min.sol",
+			nf = pareto_points(wc)),
+		f = lambda wc: expand(f"{OUTDIR}/{{nf}}/{wc.sample}.min.founders.compact.txt",
+			nf = pareto_points(wc)),
+		r = lambda wc: expand(f"{OUTDIR}/{{nf}}/{wc.sample}.flow.nrecomb.txt",
+			nf = pareto_points(wc)),
+	output:
+		f"{OUTDIR}/{{sample}}.pareto.tsv"
+	log:
+		f"{OUTDIR}/log/{{sample}}.pareto.log"
+	shell:
+		f"{SHDIR}/pareto.py"
+		f"	-s {{input.s}}"
+		f"	-f {{input.f}}"
+		f"	-r {{input.r}}"
+		f"	>{{output}} 2>{{log}}"
+
+rule pareto:
+	input:
+		expand(f"{OUTDIR}/{{sample}}.pareto.tsv",
+			sample = glob_wildcards(f"{DATADIR}/{{sample}}.gfa").sample,
+		),
@@ -0,0 +1,70 @@
+# founder inference in overlapping windows along a reference path; founder mosaics of all windows
+# are stitched into mosaics along the whole reference
+WINDOW_REF = config.get("window_reference", "")
+WINDOW_SIZE = config.get("window_size", 1000000)
+WINDOW_OVERLAP = config.get("window_overlap", 100000)
+
+checkpoint window_regions:
+	input:
+		f"{DATADIR}/{{sample}}.gfa"
+	output:
+		f"{OUTDIR}/windows/{{sample}}.regions.txt"
+	log:
+		f"{OUTDIR}/windows/log/{{sample}}.regions.log"
+	shell:
+		f"{RUSTBIN}/mkwin"
+		f"	-r '{WINDOW_REF}'"
+		f"	-s {WINDOW_SIZE}"
+		f"	-v {WINDOW_OVERLAP}"
+		f"	-c"
+		f"	{{input}}"
+		f"	>{{output}} 2>{{log}}"
+
+# windows are numbered from 1 in order along the reference
+rule extract_window:
+	input:
+		g = f"{DATADIR}/{{sample}}.gfa",
+		r = f"{OUTDIR}/windows/{{sample}}.regions.txt",
+	output:
+		f"{OUTDIR}/windows/{{sample}}.win{{w}}.gfa"
+	log:
+		f"{OUTDIR}/windows/log/{{sample}}.win{{w}}.log"
+	wildcard_constraints:
+		w = r"\d+"
+	params:
+		region = lambda wc, input: open(input.r).read().split()[int(wc.w) - 1]
+	shell:
+		f"{RUSTBIN}/subgr"
+		f"	-R '{{params.region}}'"
+		f"	{XHAPOPTS}"
+		f"	-o '^{XHAPEXP}'"
+		f"	{{input.g}}"
+		f"	>{{output}} 2>{{log}}"
+
+def window_founders(wc):
+	f = checkpoints.window_regions.get(sample=wc.sample).output[0]
+	with open(f) as data:
+		n = sum(1 for line in data if line.strip())
+	return expand(f"{OUTDIR}/{wc.pset}/{wc.sample}.win{{w}}.min.founders.long.txt",
+		w = range(1, n + 1))
+
+rule stitch_windows:
+	input:
+		g = f"{DATADIR}/{{sample}}.gfa",
+		f = window_founders,
+	output:
+		f"{OUTDIR}/{{pset}}/{{sample}}.windows.founders.long.txt"
+	log:
+		f"{OUTDIR}/{{pset}}/log/{{sample}}.windows.founders.log"
+	shell:
+		f"{RUSTBIN}/stitch"
+		f"	-g {{input.g}}"
+		f"	{{input.f}}"
+		f"	>{{output}} 2>{{log}}"
+
+rule windows:
+	input:
+		expand(f"{OUTDIR}/{{nf}}/{{sample}}.windows.founders.long.txt",
+			nf = config.get("nforced", 0),
+			sample = glob_wildcards(f"{DATADIR}/{{sample}}.gfa").sample,
+		),
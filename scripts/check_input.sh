@@ -5,20 +5,21 @@ if [ $# -ne 2 ]; then
 fi
 graph=$1
 regex=$2
+# input graphs may be compressed
+unpack="$(dirname "$0")/unpack.sh"
 
 # check if any haplotypes can be extracted and used at all
-if [[ `sed -n "/^P\s$2/p" $1 | wc -l` == 0 ]]; then
+if [[ `"$unpack" "$graph" | sed -n "/^P\s$2/p" | wc -l` == 0 ]]; then
 	echo "no haplotypes in input gfa, or none matching regex!" >1&2
 	exit 2
 fi
 
 # check for multiple sinks/sources
-awk '
+"$unpack" "$graph" | awk '
 /^P/{
-	gsub("[\\-\\+,]"," ",$3)
-	n = split($3, a, " ")
-	s[a[1]] = 1
-	S[a[n]] = 1
+	n = split($3, a, ",")
+	s[substr(a[1], 1, length(a[1])-1)] = 1
+	S[substr(a[n], 1, length(a[n])-1)] = 1
 } END {
 	if(length(s) != 1 || length(S) != 1){
 		print "unhandled multiple sources and/or sinks!"
@@ -28,4 +29,4 @@ awk '
 			print "S", i
 		exit 3
 	}
-}' $1
+}'
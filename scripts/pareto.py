@@ -0,0 +1,73 @@
+#!/usr/bin/env python3
+
+#
+# std import
+#
+from argparse import ArgumentParser, ArgumentDefaultsHelpFormatter as ADHF
+from sys import stdout, stderr, exit
+import re
+
+PAT_OBJ = re.compile(r'^#\s*Objective value\s*=\s*(\S+)')
+
+
+def read_objective(data):
+
+    for line in data:
+        m = PAT_OBJ.match(line)
+        if m:
+            return round(float(m.group(1)))
+    return None
+
+
+def read_founders(data):
+
+    # compact founder format: founder walk followed by two annotation lines
+    # whose first column is blank
+    return [line.split('\t', 1)[0].strip() for line in data if line.strip() and \
+            line[0] not in ' \t']
+
+
+if __name__ == '__main__':
+    description='''
+    Collects number of founders and recombinations of minimization solutions
+    for a sweep over the number of founders and reports the Pareto front.
+    '''
+    parser = ArgumentParser(formatter_class=ADHF, description=description)
+    parser.add_argument('-s', '--solutions', nargs='+', required=True,
+            help='Solutions of recombination minimization')
+    parser.add_argument('-f', '--founders', nargs='+', required=True,
+            help='Minimized founder sets (compact format), same order as solutions')
+    parser.add_argument('-r', '--nrecomb', nargs='*', default=[],
+            help='Recombination counts of random assignment trials, same order as solutions')
+
+    args = parser.parse_args()
+
+    if len(args.solutions) != len(args.founders) or \
+            (args.nrecomb and len(args.nrecomb) != len(args.solutions)):
+        print('number of solution, founder and recombination files must agree',
+                file=stderr)
+        exit(1)
+
+    points = list()
+    for i, (s, f) in enumerate(zip(args.solutions, args.founders)):
+        obj = read_objective(open(s))
+        if obj is None:
+            print(f'no objective value in {s}, skipping', file=stderr)
+            continue
+        founders = read_founders(open(f))
+        nr = args.nrecomb and open(args.nrecomb[i]).read().strip() or 'NA'
+        points.append((len(founders), obj, nr, ','.join(founders), f))
+
+    points.sort()
+    out = stdout
+    print('\t'.join(('founders', 'recombinations', 'recombinations_random',
+        'pareto_optimal', 'founder_set', 'founder_set_file')), file=out)
+    best = None
+    for nf, obj, nr, fs, f in points:
+        # a point is Pareto-optimal if no point with fewer (or equally many)
+        # founders has at most as many recombinations
+        optimal = best is None or obj < best
+        if optimal:
+            best = obj
+        print('\t'.join(map(str, (nf, obj, nr, optimal and 1 or 0, fs,
+            f))), file=out)
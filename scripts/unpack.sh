@@ -0,0 +1,11 @@
+#!/bin/bash -e
+# usage: $0 [FILE..]
+# write files (or standard input) to standard output, decompressing gzip or zstd on the fly
+for f in "${@:--}"; do
+	# zstd is recognized by its magic number, zcat passes anything but gzip through as is
+	if [ "$f" != - ] && [ "$(head -c 4 "$f" | od -An -tx1 | tr -d ' \n')" = 28b52ffd ]; then
+		zstdcat "$f"
+	else
+		zcat -f "$f"
+	fi
+done
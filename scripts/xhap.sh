@@ -1,29 +1,64 @@
 #!/bin/bash
 # extract haplotypes from gfa with +- path format and convert to <> format
-# usage: $0 [-p pattern] [gfa..]
+# usage: $0 [-p pattern] [-s samplefile] [-H hapindex] [-c contig] [gfa..]
 # -p: pattern to filter path records by
+# -s: file listing PanSN sample names (sample#hap#contig) to keep, one per line
+# -H: PanSN haplotype index to keep
+# -c: PanSN contig name to keep
 PAT=H
-if [ $1 = "-p" ]; then
-	PAT=$2
-		shift 2
-fi
+SAMPLES=
+HAP=
+CONTIG=
+while getopts "p:s:H:c:" opt; do
+	case $opt in
+	p) PAT=$OPTARG ;;
+	s) SAMPLES=$OPTARG ;;
+	H) HAP=$OPTARG ;;
+	c) CONTIG=$OPTARG ;;
+	*)
+		echo usage: "$0 [-p pattern] [-s samplefile] [-H hapindex] [-c contig] [gfa..]" 1>&2
+		exit 1
+		;;
+	esac
+done
+shift $((OPTIND - 1))
 if [ -z $PAT ]; then
-	echo usage: "$0 [-p pattern] [gfa..]" 1>&2
+	echo usage: "$0 [-p pattern] [-s samplefile] [-H hapindex] [-c contig] [gfa..]" 1>&2
 	exit 1
 fi
-awk -v "p=^$PAT" -v 'OFS=\t' '
+# input graphs may be compressed
+"$(dirname "$0")"/unpack.sh "$@" | awk -v "p=^$PAT" -v "sf=$SAMPLES" -v "hap=$HAP" -v "contig=$CONTIG" -v 'OFS=\t' '
+BEGIN{
+	if(sf != "")
+		while((getline l < sf) > 0)
+			if(l != "" && l !~ /^#/)
+				S[l] = 1
+}
 $1 == "P" && $2 ~ p{
+	# select by PanSN name components
+	k = split($2, pn, "#")
+	if(sf != "" && !(pn[1] in S))
+		next
+	if(hap != "" && (k < 2 || pn[2] != hap))
+		next
+	if(contig != "" && (k < 3 || pn[3] != contig))
+		next
 	n = split($3, a, ",")
 	r = ""
+	# orientation is the last character of a step, segment names may contain "+" or "-"
 	for(i=1; i<=n; i++){
-		m = split(a[i], b, "+")
-		s = ">"
-		if(m == 1){
-			m = split(a[i], b, "-")
-			s = "<"
-		}
-		r = r s b[1]
+		l = length(a[i])
+		s = substr(a[i], l) == "-" ? "<" : ">"
+		r = r s substr(a[i], 1, l-1)
 	}
-	print $2, r
+	# pass on haplotype weight, if given as WT tag
+	w = ""
+	for(i=5; i<=NF; i++)
+		if($i ~ /^WT:[if]:/)
+			w = $i
+	if(w != "")
+		print $2, r, w
+	else
+		print $2, r
 }
-' "$@"
+'
@@ -0,0 +1,91 @@
+/* standard use */
+use std::io::{self, Write};
+
+/* crate use */
+use clap::Parser;
+
+/* private use */
+use founderset as ff;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    version = "0.1",
+    author = "Daniel Doerr <daniel.doerr@hhu.de>",
+    about = "Report (nested) superbubbles and cut nodes of a variation graph"
+)]
+pub struct Command {
+    #[clap(index = 1, help = "graph in GFA1 format", required = true)]
+    pub graph: String,
+
+    #[clap(
+        short = 'c',
+        long = "cut-nodes",
+        help = "Write cut nodes, whose removal disconnects the graph, to file (one per line)"
+    )]
+    pub cut_nodes: Option<String>,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+fn main() -> Result<(), io::Error> {
+    env_logger::init();
+
+    // initialize command line parser & parse command line arguments
+    let params = Command::parse();
+
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+
+    log::info!("loading graph {}", &params.graph);
+    let ff::StreamedGFA { graph, names, .. } =
+        ff::read_gfa_file(&params.graph, &ff::GFAReadOptions::default())?;
+
+    log::info!("decomposing graph into superbubbles");
+    let d = ff::decompose_bubbles(&graph);
+    log::info!(
+        "identified {} superbubbles ({} top-level) and {} cut nodes",
+        d.bubbles.len(),
+        d.bubbles.iter().filter(|b| b.parent.is_none()).count(),
+        d.cut_nodes.len()
+    );
+
+    // bubbles are numbered from 1 in output
+    writeln!(out, "bubble\tparent\tdepth\tentry\texit\tinterior")?;
+    for (i, b) in d.bubbles.iter().enumerate() {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}\t{}\t{}",
+            i + 1,
+            b.parent
+                .map(|p| (p + 1).to_string())
+                .unwrap_or_else(|| String::from("*")),
+            b.depth,
+            names.v2str(&b.entry),
+            names.v2str(&b.exit),
+            b.interior
+                .iter()
+                .map(|&v| names.name(v as usize))
+                .collect::<Vec<String>>()
+                .join(",")
+        )?;
+    }
+    ff::finish(out)?;
+
+    if let Some(file) = &params.cut_nodes {
+        log::info!("writing cut nodes to {}", file);
+        let mut cut_nodes: Vec<u64> = d.cut_nodes.iter().copied().collect();
+        cut_nodes.sort_unstable();
+        let mut c = ff::create_file(file)?;
+        for v in cut_nodes {
+            writeln!(c, "{}", names.name(v as usize))?;
+        }
+        ff::finish(c)?;
+    }
+
+    log::info!("done");
+    Ok(())
+}
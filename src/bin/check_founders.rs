@@ -0,0 +1,239 @@
+/* standard use */
+use std::io::{self, Write};
+
+/* crate use */
+use clap::Parser;
+use handlegraph::{
+    handle::{Direction, Edge, Handle},
+    handlegraph::*,
+    hashgraph::HashGraph,
+};
+use itertools::Itertools;
+use rustc_hash::FxHashSet;
+
+/* private use */
+use founderset as ff;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    version = "0.1",
+    author = "Daniel Doerr <daniel.doerr@hhu.de>",
+    about = "Verify founder set against the graph and (optionally) the haplotypes it was constructed from"
+)]
+pub struct Command {
+    #[clap(index = 1, help = "graph in GFA1 format", required = true)]
+    pub graph: String,
+
+    #[clap(index = 2, help = "founder sequences", required = true)]
+    pub founder_set: String,
+
+    #[clap(
+        short = 'H',
+        long = "haplotypes",
+        help = "Check that haplotype adjacencies are covered by founders and that founders are colorable by haplotypes"
+    )]
+    pub haplotypes: Option<String>,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+// a single violation, written as tab-separated line of check, walk name, position in walk, and
+// offending node or adjacency
+struct Violation {
+    check: &'static str,
+    name: String,
+    pos: Option<usize>,
+    detail: String,
+}
+
+impl Violation {
+    fn new(check: &'static str, name: &str, pos: Option<usize>, detail: String) -> Self {
+        Violation {
+            check,
+            name: name.to_string(),
+            pos,
+            detail,
+        }
+    }
+}
+
+// adjacencies of a walk in canonical orientation
+fn canonical(u: &Handle, v: &Handle) -> (Handle, Handle) {
+    std::cmp::min((*u, *v), (v.flip(), u.flip()))
+}
+
+fn check_founders_in_graph(
+    graph: &HashGraph,
+    founders: &[(String, Vec<Handle>)],
+    names: &ff::SegmentNames,
+    res: &mut Vec<Violation>,
+) {
+    let mut covered: FxHashSet<(Handle, Handle)> = FxHashSet::default();
+
+    for (name, walk) in founders.iter() {
+        if walk.is_empty() {
+            res.push(Violation::new("empty_walk", name, None, String::from("-")));
+            continue;
+        }
+        for (i, v) in walk.iter().enumerate() {
+            if !graph.has_node(v.id()) {
+                res.push(Violation::new(
+                    "missing_node",
+                    name,
+                    Some(i),
+                    names.v2str(v),
+                ));
+            }
+        }
+        for (i, (u, v)) in walk.iter().tuple_windows().enumerate() {
+            if graph.has_node(u.id()) && graph.has_node(v.id()) && !graph.has_edge(*u, *v) {
+                res.push(Violation::new(
+                    "missing_edge",
+                    name,
+                    Some(i),
+                    format!("{}{}", names.v2str(u), names.v2str(v)),
+                ));
+            }
+            covered.insert(canonical(u, v));
+        }
+
+        // founders must enter the graph at a side without incoming edges and leave it at a side
+        // without outgoing edges, corresponding to sources and sinks of the flow network
+        let (first, last) = (walk.first().unwrap(), walk.last().unwrap());
+        if graph.has_node(first.id()) && graph.degree(*first, Direction::Left) > 0 {
+            res.push(Violation::new(
+                "invalid_start",
+                name,
+                Some(0),
+                names.v2str(first),
+            ));
+        }
+        if graph.has_node(last.id()) && graph.degree(*last, Direction::Right) > 0 {
+            res.push(Violation::new(
+                "invalid_end",
+                name,
+                Some(walk.len() - 1),
+                names.v2str(last),
+            ));
+        }
+    }
+
+    for Edge(u, v) in graph.edges().sorted() {
+        if !covered.contains(&canonical(&u, &v)) {
+            res.push(Violation::new(
+                "uncovered_edge",
+                "-",
+                None,
+                format!("{}{}", names.v2str(&u), names.v2str(&v)),
+            ));
+        }
+    }
+}
+
+fn check_founders_with_haplotypes(
+    founders: &[(String, Vec<Handle>)],
+    haplotypes: &[(String, Vec<Handle>)],
+    names: &ff::SegmentNames,
+    res: &mut Vec<Violation>,
+) {
+    let founder_adjs = ff::haplotype_to_adj_map(founders);
+    let hap_adjs = ff::haplotype_to_adj_map(haplotypes);
+
+    // each haplotype adjacency must be traversed by some founder, otherwise the haplotype cannot
+    // be composed from founder segments
+    for (name, walk) in haplotypes.iter() {
+        for (i, (u, v)) in walk.iter().tuple_windows().enumerate() {
+            if !founder_adjs.contains_key(&(*u, *v)) {
+                res.push(Violation::new(
+                    "uncovered_adjacency",
+                    name,
+                    Some(i),
+                    format!("{}{}", names.v2str(u), names.v2str(v)),
+                ));
+            }
+        }
+    }
+
+    // conversely, coloring founders with haplotypes (as done by min_random) requires each founder
+    // adjacency to be traversed by some haplotype
+    for (name, walk) in founders.iter() {
+        for (i, (u, v)) in walk.iter().tuple_windows().enumerate() {
+            if !hap_adjs.contains_key(&(*u, *v)) {
+                res.push(Violation::new(
+                    "uncolorable_adjacency",
+                    name,
+                    Some(i),
+                    format!("{}{}", names.v2str(u), names.v2str(v)),
+                ));
+            }
+        }
+    }
+}
+
+fn main() -> Result<(), io::Error> {
+    env_logger::init();
+
+    // initialize command line parser & parse command line arguments
+    let params = Command::parse();
+
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+
+    log::info!("loading graph {}", &params.graph);
+    let ff::StreamedGFA { graph, names, .. } =
+        ff::read_gfa_file(&params.graph, &ff::GFAReadOptions::default())?;
+
+    log::info!("loading founder sequences from {}", params.founder_set);
+    let founders = ff::read_walks(ff::open_file(&params.founder_set)?, &names)
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    log::info!("parsed {} founders", founders.len());
+
+    let mut violations: Vec<Violation> = Vec::new();
+    check_founders_in_graph(&graph, &founders, &names, &mut violations);
+    // empty walks are reported as violations above, but have no adjacencies to compare
+    let founders: Vec<(String, Vec<Handle>)> = founders
+        .into_iter()
+        .filter(|(_, walk)| !walk.is_empty())
+        .collect();
+
+    if let Some(h) = &params.haplotypes {
+        log::info!("loading haplotype sequences from {}", h);
+        let haplotypes = ff::read_haplotype_fragments(ff::open_file(h)?, &names)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        log::info!("parsed {} haplotype fragments", haplotypes.len());
+        check_founders_with_haplotypes(&founders, &haplotypes, &names, &mut violations);
+    }
+
+    writeln!(out, "#check\tname\tposition\tdetail")?;
+    for v in violations.iter() {
+        writeln!(
+            out,
+            "{}\t{}\t{}\t{}",
+            v.check,
+            v.name,
+            v.pos
+                .map(|i| i.to_string())
+                .unwrap_or_else(|| String::from("-")),
+            v.detail
+        )?;
+    }
+    ff::finish(out)?;
+
+    if !violations.is_empty() {
+        for (check, c) in violations.iter().map(|v| v.check).counts().iter().sorted() {
+            log::error!("{} violation(s) of type {}", c, check);
+        }
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("founder set violates {} constraint(s)", violations.len()),
+        ));
+    }
+    log::info!("founder set is valid");
+
+    log::info!("done");
+    Ok(())
+}
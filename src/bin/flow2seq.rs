@@ -3,9 +3,10 @@ use std::io::{self, Write};
 
 /* crate use */
 use clap::Parser;
-use handlegraph::handle::Handle;
+use handlegraph::{handle::Handle, hashgraph::HashGraph};
 use itertools::Itertools;
 use petgraph::{dot::Dot, graphmap::UnGraphMap};
+use rand::{rngs::ThreadRng, Rng};
 use rustc_hash::{FxHashMap, FxHashSet};
 
 /* private use */
@@ -20,20 +21,115 @@ use founderset as ff;
 pub struct Command {
     #[clap(index = 1, help = "Solution file from Gurobi run", required = true)]
     pub flow_solution: String,
+
+    #[clap(
+        short = 'g',
+        long = "graph",
+        help = "Validate flow solution against the linear program of the given graph (GFA1 format) before decomposition, and report founders by its segment names"
+    )]
+    pub graph: Option<String>,
+
+    #[clap(
+        short = 'f',
+        long = "fasta",
+        help = "Write spelled founder sequences in FASTA format to file, taking into account link overlaps",
+        requires = "graph"
+    )]
+    pub fasta: Option<String>,
+
+    #[clap(
+        short = 'd',
+        long = "deterministic",
+        help = "Choose successors and merge points in a reproducible order instead of hash order"
+    )]
+    pub deterministic: bool,
+
+    #[clap(
+        short = 'H',
+        long = "haplotypes",
+        help = "Choose successors and merge points that follow the given haplotypes (implies --deterministic)"
+    )]
+    pub haplotypes: Option<String>,
+
+    #[clap(
+        short = 'k',
+        long = "samples",
+        help = "Sample given number of alternative founder sets and output the one with fewest recombinations",
+        requires = "haplotypes"
+    )]
+    pub samples: Option<usize>,
+
+    #[clap(
+        short = 'M',
+        long = "mismatch-penalty",
+        help = "Penalty for each founder adjacency not traversed by any haplotype when scoring sampled founder sets",
+        default_value = "1"
+    )]
+    pub mismatch_penalty: usize,
+
+    #[clap(
+        short = 'a',
+        long = "alternatives",
+        help = "Write all distinct sampled founder sets and their number of recombinations to file",
+        requires = "samples"
+    )]
+    pub alternatives: Option<String>,
+
+    #[clap(
+        short = 'D',
+        long = "dump-graph",
+        help = "Write component graph, with the merges of circular components highlighted, to <PREFIX>.dot and <PREFIX>.graphml for inspection"
+    )]
+    pub dump_graph: Option<String>,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+// how walks are extracted from the flow and how circular components are merged
+pub struct Strategy<'a> {
+    pub deterministic: bool,
+    pub adjs: Option<&'a ff::AdjacencyMap>,
+    // if set, successors and merge points are drawn at random
+    pub rng: Option<ThreadRng>,
+}
+
+impl Strategy<'_> {
+    fn extract_walk(
+        &mut self,
+        edges: &mut FxHashMap<ff::Extremity, FxHashSet<(ff::Extremity, usize)>>,
+        start: &ff::Extremity,
+    ) -> Vec<Handle> {
+        if let Some(rng) = &mut self.rng {
+            ff::extract_sampled_walk_from_flow(edges, start, rng)
+        } else if self.deterministic {
+            ff::extract_guided_walk_from_flow(edges, start, self.adjs)
+        } else {
+            ff::extract_random_walk_from_flow(edges, start)
+        }
+    }
 }
 
 pub fn extract_linear_components(
     edges: &mut FxHashMap<ff::Extremity, FxHashSet<(ff::Extremity, usize)>>,
     flow: &ff::Flow,
+    strategy: &mut Strategy,
 ) -> Vec<Vec<Handle>> {
     let mut res: Vec<Vec<Handle>> = Vec::new();
 
     log::info!("extracting linear components");
-    for v in flow.sources.iter() {
+    let mut sources: Vec<&ff::Extremity> = flow.sources.iter().collect();
+    if strategy.deterministic {
+        sources.sort();
+    }
+    for v in sources {
         let w = flow.nodes.get(&(v.clone(), ff::Direction::In)).unwrap();
         log::info!("extracting {} linear components start at source {}", w, v);
         for _ in 0..*w {
-            let s = ff::extract_random_walk_from_flow(
+            let s = strategy.extract_walk(
                 edges,
                 &ff::Extremity {
                     id: v.id,
@@ -54,15 +150,20 @@ pub fn extract_linear_components(
 
 pub fn extract_circular_components(
     edges: &mut FxHashMap<ff::Extremity, FxHashSet<(ff::Extremity, usize)>>,
+    strategy: &mut Strategy,
 ) -> Vec<Vec<Handle>> {
     let mut res: Vec<Vec<Handle>> = Vec::new();
 
     while !edges.is_empty() {
-        let v = edges.keys().next().unwrap().clone();
+        let v = if strategy.deterministic {
+            edges.keys().min().unwrap().clone()
+        } else {
+            edges.keys().next().unwrap().clone()
+        };
         let w: usize = edges.get(&v).unwrap().iter().map(|x| x.1).sum();
         if w > 0 {
             log::info!("extracting circular component starting at {}", v);
-            let c = ff::extract_random_walk_from_flow(edges, &v);
+            let c = strategy.extract_walk(edges, &v);
             log::debug!("circular flow sequence: {:?}", ff::v2seq(&c, ""));
             res.push(c);
         } else {
@@ -125,20 +226,124 @@ pub fn construct_component_graph(components: &Vec<Vec<Handle>>) -> UnGraphMap<us
     graph
 }
 
+// number of haplotype-supported node contexts that arise from merging the circular donor into the
+// recipient at donor position i and recipient position j, which both hold the same node
+fn merge_score(
+    adjs: &ff::AdjacencyMap,
+    recipient: &[Handle],
+    j: usize,
+    donor: &[Handle],
+    i: usize,
+) -> usize {
+    // last element of circular donor is identical to the first one
+    let n = donor.len() - 1;
+    let r = recipient[j];
+    // predecessor and successor of the recombination node in the donor, oriented like the recipient
+    let (a, b) = if donor[i].is_reverse() == r.is_reverse() {
+        (donor[(i + n - 1) % n], donor[(i + 1) % n])
+    } else {
+        (donor[(i + 1) % n].flip(), donor[(i + n - 1) % n].flip())
+    };
+    let supported = |p: Handle, q: Handle| match adjs.get(&(p, r)) {
+        None => false,
+        Some(pos) => !ff::continue_adj(adjs, pos, &(r, q)).is_empty(),
+    };
+
+    let mut score = 0;
+    if j > 0 && supported(recipient[j - 1], b) {
+        score += 1;
+    }
+    if j + 1 < recipient.len() && supported(a, recipient[j + 1]) {
+        score += 1;
+    }
+    score
+}
+
+// choose recombination node and its positions in donor and recipient at which both are merged
+fn choose_merge_point(
+    recombination_nodes: &[u64],
+    recipient: &[Handle],
+    donor: &[Handle],
+    strategy: &mut Strategy,
+) -> (usize, usize) {
+    let first_pos = |seq: &[Handle], r: u64| {
+        seq.iter()
+            .position(|x| x.unpack_number() == r)
+            .expect("recombination node not contained in sequence")
+    };
+
+    if let Some(rng) = &mut strategy.rng {
+        let r = recombination_nodes[rng.gen_range(0..recombination_nodes.len())];
+        let is = (0..donor.len() - 1)
+            .filter(|&i| donor[i].unpack_number() == r)
+            .collect::<Vec<usize>>();
+        let js = (0..recipient.len())
+            .filter(|&j| recipient[j].unpack_number() == r)
+            .collect::<Vec<usize>>();
+        return (
+            is[rng.gen_range(0..is.len())],
+            js[rng.gen_range(0..js.len())],
+        );
+    }
+
+    match strategy.adjs {
+        None => {
+            let r = if strategy.deterministic {
+                *recombination_nodes.iter().min().unwrap()
+            } else {
+                // just pick the first one
+                recombination_nodes[0]
+            };
+            (first_pos(donor, r), first_pos(recipient, r))
+        }
+        Some(adjs) => {
+            let mut nodes = recombination_nodes.to_vec();
+            nodes.sort_unstable();
+            let mut best = None;
+            for r in nodes {
+                for j in (0..recipient.len()).filter(|&j| recipient[j].unpack_number() == r) {
+                    for i in (0..donor.len() - 1).filter(|&i| donor[i].unpack_number() == r) {
+                        let score = merge_score(adjs, recipient, j, donor, i);
+                        if best.map(|(s, _, _)| score > s).unwrap_or(true) {
+                            best = Some((score, i, j));
+                        }
+                    }
+                }
+            }
+            let (score, i, j) = best.unwrap();
+            log::debug!(
+                "merging at {} supported by {} haplotype node contexts",
+                ff::v2str(&recipient[j]),
+                score
+            );
+            (i, j)
+        }
+    }
+}
+
+// founder sequences along with the edges of the component graph along which components were merged
 fn build_founder_sequences(
     graph: &UnGraphMap<usize, Vec<u64>>,
     components: &Vec<Vec<Handle>>,
     n_linear: usize,
-) -> Vec<Vec<Handle>> {
+    strategy: &mut Strategy,
+) -> (Vec<Vec<Handle>>, Vec<(usize, usize)>) {
     log::info!("building founder sequences");
 
     let mut res: Vec<Vec<Handle>> = components[..n_linear].to_vec();
 
     let neighbors: Vec<Vec<usize>> = (0..components.len())
-        .map(|v| graph.neighbors(v).collect())
+        .map(|v| {
+            let mut n: Vec<usize> = graph.neighbors(v).collect();
+            if strategy.deterministic {
+                n.sort_unstable();
+            }
+            n
+        })
         .collect();
     let mut queue: Vec<(usize, usize)> = (0..n_linear).zip(vec![0; n_linear]).collect();
 
+    let mut merges: Vec<(usize, usize)> = Vec::new();
     let mut merged_with: Vec<usize> = (0..n_linear).collect();
     merged_with.extend(vec![std::usize::MAX; components.len() - n_linear]);
 
@@ -152,14 +357,13 @@ fn build_founder_sequences(
                 let mut donor = components[v].clone();
                 let recipient = &mut res[merged_with[u]];
 
-                // just pick the first one
-                let recombination_node = graph.edge_weight(u, v).unwrap()[0];
+                let (i, j) = choose_merge_point(
+                    graph.edge_weight(u, v).unwrap(),
+                    recipient,
+                    &donor,
+                    strategy,
+                );
 
-                // prepare donor
-                let mut i = 0;
-                while donor[i].unpack_number() != recombination_node {
-                    i += 1;
-                }
                 // remove last node -- which must be identical to the first node
                 donor.pop();
                 log::debug!(
@@ -174,11 +378,6 @@ fn build_founder_sequences(
                     ff::v2seq(&donor, "")
                 );
 
-                // move cursor to recombination node of donor, where the recepient will be inserted
-                let mut j = 0;
-                while recipient[j].unpack_number() != recombination_node {
-                    j += 1;
-                }
                 log::debug!(
                     "recombinations nodes: {} -- {}",
                     ff::v2str(&recipient[j]),
@@ -204,6 +403,7 @@ fn build_founder_sequences(
                 log::debug!("merged sequence: {}", ff::v2seq(&recipient, ""));
 
                 merged_with[v] = merged_with[u];
+                merges.push((u, v));
                 queue.push((u, e + 1));
                 queue.push((v, 0));
             } else {
@@ -212,30 +412,266 @@ fn build_founder_sequences(
         }
     }
 
-    res
+    (res, merges)
 }
 
-fn main() -> Result<(), io::Error> {
-    env_logger::init();
+// component graph in which each component is colored by the founder it ends up in
+fn dump_component_graph(
+    graph: &UnGraphMap<usize, Vec<u64>>,
+    components: &[Vec<Handle>],
+    n_linear: usize,
+    merges: &[(usize, usize)],
+    names: &ff::SegmentNames,
+) -> ff::GraphDump {
+    let mut founder: Vec<Option<usize>> = (0..components.len())
+        .map(|i| if i < n_linear { Some(i) } else { None })
+        .collect();
+    // merges are recorded in the order in which they happened
+    for &(u, v) in merges.iter() {
+        founder[v] = founder[u];
+    }
+    let merged: FxHashSet<(usize, usize)> =
+        merges.iter().map(|&(u, v)| (u.min(v), u.max(v))).collect();
 
-    // print output to stdout
-    let mut out = io::BufWriter::new(io::stdout());
+    let mut edges: Vec<(usize, usize, &Vec<u64>)> = graph
+        .all_edges()
+        .map(|(u, v, w)| (u.min(v), u.max(v), w))
+        .collect();
+    edges.sort_unstable_by_key(|(u, v, _)| (*u, *v));
 
-    // initialize command line parser & parse command line arguments
-    let params = Command::parse();
+    ff::GraphDump {
+        directed: false,
+        nodes: components
+            .iter()
+            .enumerate()
+            .map(|(i, c)| ff::DumpNode {
+                id: format!("c{}", i + 1),
+                label: format!(
+                    "{} {}: {}",
+                    if i < n_linear { "linear" } else { "circular" },
+                    i + 1,
+                    names.v2seq(c, "")
+                ),
+                color: founder[i],
+            })
+            .collect(),
+        edges: edges
+            .into_iter()
+            .map(|(u, v, w)| {
+                let is_merge = merged.contains(&(u, v));
+                ff::DumpEdge {
+                    source: format!("c{}", u + 1),
+                    target: format!("c{}", v + 1),
+                    label: w.iter().sorted().map(|&x| names.name(x as usize)).join(","),
+                    style: if is_merge {
+                        ff::EdgeType::Solid
+                    } else {
+                        ff::EdgeType::Dashed
+                    },
+                    highlight: is_merge,
+                }
+            })
+            .collect(),
+    }
+}
 
-    let f: ff::Flow = ff::read_flow(&params.flow_solution)?;
-    f.log_sources_sinks();
+// without graph, only those constraints are checked that do not depend on it
+fn validate(
+    file: &String,
+    values: &[(String, f64)],
+    graph: Option<&HashGraph>,
+) -> Result<(), io::Error> {
+    log::info!("validating flow solution");
+    let violations = match graph {
+        None => ff::validate_flow_values(values),
+        Some(g) => ff::validate_flow(values, g),
+    };
+    if violations.is_empty() {
+        log::info!("flow solution is valid");
+        return Ok(());
+    }
+    for v in violations.iter() {
+        log::error!("{}", v);
+    }
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!(
+            "flow solution {} violates {} constraint(s)",
+            file,
+            violations.len()
+        ),
+    ))
+}
 
+fn decompose(
+    f: &ff::Flow,
+    strategy: &mut Strategy,
+    dump: Option<(&str, &ff::SegmentNames)>,
+) -> Result<Vec<Vec<Handle>>, io::Error> {
     let mut edges = f.edges.clone();
-    let mut components = extract_linear_components(&mut edges, &f);
+    let mut components = extract_linear_components(&mut edges, f, strategy);
     let linear_n = components.len();
-    components.extend(extract_circular_components(&mut edges));
+    components.extend(extract_circular_components(&mut edges, strategy));
     let graph = construct_component_graph(&components);
 
-    let founders = build_founder_sequences(&graph, &components, linear_n);
-    ff::write_founders(&founders, &mut out)?;
-    out.flush()?;
+    let (founders, merges) = build_founder_sequences(&graph, &components, linear_n, strategy);
+    if let Some((prefix, names)) = dump {
+        dump_component_graph(&graph, &components, linear_n, &merges, names).write_files(prefix)?;
+    }
+    Ok(founders)
+}
+
+// samples k further decompositions of the flow and returns the founder set with fewest
+// recombinations, which is either one of the samples or the given initial founder set
+fn sample_decompositions(
+    f: &ff::Flow,
+    initial: Vec<Vec<Handle>>,
+    score_of: &dyn Fn(&[Handle]) -> usize,
+    k: usize,
+    alternatives: &Option<String>,
+    names: &ff::SegmentNames,
+) -> Result<Vec<Vec<Handle>>, io::Error> {
+    log::info!("sampling {} alternative founder sets", k);
+
+    let mut strategy = Strategy {
+        deterministic: true,
+        adjs: None,
+        rng: Some(rand::thread_rng()),
+    };
+
+    // distinct founder sets, identified by their sorted founder sequences, along with their
+    // number of recombinations and how often they have been sampled
+    let mut distinct: Vec<(Vec<Vec<Handle>>, usize, usize)> = Vec::new();
+    let mut index: FxHashMap<Vec<String>, usize> = FxHashMap::default();
+    let mut scores: Vec<usize> = Vec::new();
+
+    for founders in
+        std::iter::once(Ok(initial)).chain((0..k).map(|_| decompose(f, &mut strategy, None)))
+    {
+        let founders = founders?;
+        let key: Vec<String> = founders.iter().map(|x| ff::v2seq(x, "")).sorted().collect();
+        let score = match index.get(&key) {
+            Some(&i) => {
+                distinct[i].2 += 1;
+                distinct[i].1
+            }
+            None => {
+                let score = founders.iter().map(|x| score_of(x)).sum();
+                index.insert(key, distinct.len());
+                distinct.push((founders, score, 1));
+                score
+            }
+        };
+        scores.push(score);
+    }
+
+    log::info!(
+        "sampled {} distinct founder sets, recombinations min {} / mean {:.2} / max {}",
+        distinct.len(),
+        scores.iter().min().unwrap(),
+        scores.iter().sum::<usize>() as f64 / scores.len() as f64,
+        scores.iter().max().unwrap()
+    );
+
+    // stable sort keeps the initial founder set first among equally good ones
+    distinct.sort_by_key(|(_, score, _)| *score);
+
+    if let Some(file) = alternatives {
+        let mut out = ff::create_file(file)?;
+        for (i, (founders, score, count)) in distinct.iter().enumerate() {
+            writeln!(
+                out,
+                "# alternative {}: {} recombinations, sampled {} times",
+                i + 1,
+                score,
+                count
+            )?;
+            ff::write_founders(founders, names, &mut out)?;
+        }
+        ff::finish(out)?;
+    }
+
+    Ok(distinct.swap_remove(0).0)
+}
+
+fn main() -> Result<(), io::Error> {
+    env_logger::init();
+
+    // initialize command line parser & parse command line arguments
+    let params = Command::parse();
+
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+
+    let values = ff::read_flow_values(&params.flow_solution)?;
+    let gfa = match &params.graph {
+        None => None,
+        Some(g) => {
+            log::info!("loading graph {}", g);
+            let gfa = ff::read_gfa_file(
+                g,
+                &ff::GFAReadOptions {
+                    sequences: params.fasta.is_some(),
+                    ..Default::default()
+                },
+            )?;
+            Some(gfa)
+        }
+    };
+    validate(
+        &params.flow_solution,
+        &values,
+        gfa.as_ref().map(|x| &x.graph),
+    )?;
+    let names = gfa.as_ref().map(|x| x.names.clone()).unwrap_or_default();
+    let f: ff::Flow = ff::flow_from_values(&values);
+    f.log_sources_sinks();
+
+    let haplotypes = match &params.haplotypes {
+        None => Vec::new(),
+        Some(h) => {
+            log::info!("loading haplotype sequences from {}", h);
+            ff::read_haplotype_fragments(ff::open_file(h)?, &names)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?
+        }
+    };
+    let adjs = ff::haplotype_to_adj_map(&haplotypes);
+    let mut strategy = Strategy {
+        deterministic: params.deterministic || params.haplotypes.is_some(),
+        adjs: params.haplotypes.as_ref().map(|_| &adjs),
+        rng: None,
+    };
+
+    let mut founders = decompose(
+        &f,
+        &mut strategy,
+        params.dump_graph.as_deref().map(|p| (p, &names)),
+    )?;
+    if let Some(k) = params.samples {
+        founders = sample_decompositions(
+            &f,
+            founders,
+            // founders may traverse adjacencies of the graph that none of the haplotypes does,
+            // e.g., if haplotypes are a subset of the graph's paths
+            &|x| ff::count_recombinations(x, &adjs, Some(params.mismatch_penalty)),
+            k,
+            &params.alternatives,
+            &names,
+        )?;
+    }
+    ff::write_founders(&founders, &names, &mut out)?;
+    ff::finish(out)?;
+
+    if let (Some(file), Some(gfa)) = (&params.fasta, &gfa) {
+        log::info!("writing founder sequences to {}", file);
+        let mut fasta = ff::create_file(file)?;
+        for (i, f) in founders.iter().enumerate() {
+            writeln!(fasta, ">founder_seq{}", i + 1)?;
+            fasta.write_all(&ff::spell_walk(&gfa.graph, f, &gfa.annotations.overlaps))?;
+            writeln!(fasta)?;
+        }
+        ff::finish(fasta)?;
+    }
 
     log::info!("done");
     Ok(())
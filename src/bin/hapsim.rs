@@ -3,11 +3,9 @@ use std::cmp::Reverse;
 use std::collections::{BinaryHeap, HashSet};
 use std::convert::TryInto;
 use std::io;
-use std::io::prelude::*;
 
 /* crate use */
 use clap::Parser;
-use gfa::{gfa::GFA, parser::GFAParser};
 use handlegraph::{
     handle::{Direction, Edge, Handle, NodeId},
     handlegraph::IntoNeighbors,
@@ -89,6 +87,12 @@ struct Args {
     )]
     infile: String,
 
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    compress: Option<ff::Compression>,
+
     nhaplotypes: usize,
 }
 
@@ -266,10 +270,24 @@ fn mk_graph(fnd: Vec<Vec<(usize, bool)>>) -> HashGraph {
  * must always be length+1.
  * neither is not checked for!
  */
-fn read_founders(args: &Args) -> HashGraph {
-    let p = GFAParser::new();
-    let gfa: GFA<usize, ()> = p.parse_file(&args.infile).unwrap();
-    HashGraph::from_gfa(&gfa)
+fn read_founders(args: &Args) -> (HashGraph, ff::SegmentNames, ff::GFAAnnotations) {
+    let select_all = |_: &str| true;
+    let ff::StreamedGFA {
+        graph,
+        names,
+        annotations,
+        ..
+    } = ff::read_gfa_file(
+        &args.infile,
+        &ff::GFAReadOptions {
+            sequences: true,
+            paths: Some(&select_all),
+            tags: true,
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    (graph, names, annotations)
 }
 
 /* generate founder in O(nnodes + log ndups);  nodes are (index, direction),
@@ -377,17 +395,20 @@ fn main() -> Result<(), std::io::Error> {
     };
 
     let mut rng = rand::thread_rng();
-    let mut g;
-    if args.infile.is_empty() {
+    let (mut g, names, annotations) = if args.infile.is_empty() {
         let fnd = mk_founders(&args, ndups, &mut rng);
-        g = mk_graph(fnd);
+        (
+            mk_graph(fnd),
+            ff::SegmentNames::default(),
+            ff::GFAAnnotations::default(),
+        )
     } else {
-        g = read_founders(&args);
-    }
+        read_founders(&args)
+    };
 
-    let mut out = io::BufWriter::new(std::io::stdout());
+    let mut out = ff::compress(io::stdout(), args.compress)?;
     if args.nhaplotypes < 1 || args.nohap {
-        ff::write_gfa(&g, &mut out)?;
+        ff::write_gfa(&g, &names, &annotations, &mut out)?;
     } else {
         let (ns, es) = mk_haplotypes(&mut g, &args, &mut rng);
         ff::write_subset_gfa(
@@ -395,10 +416,12 @@ fn main() -> Result<(), std::io::Error> {
             &ns,
             &es,
             &g.path_ids().collect::<Vec<PathId>>(),
+            &names,
+            &annotations,
             &mut out,
         )?;
     }
-    out.flush()?;
+    ff::finish(out)?;
 
     log::info!("done");
     Ok(())
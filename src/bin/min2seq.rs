@@ -1,11 +1,11 @@
 /* standard use */
 use std::convert::TryInto;
-use std::fs;
-use std::io::{self, BufRead, BufReader, Seek, Write};
+use std::io::{self, BufRead, Read, Seek, Write};
 use std::str::FromStr;
 
 /* crate use */
 use clap::Parser;
+use handlegraph::handle::Handle;
 use itertools::Itertools;
 use petgraph::graphmap::DiGraphMap;
 use petgraph::Outgoing;
@@ -41,8 +41,43 @@ pub struct Args {
     )]
     pub compact: bool,
 
+    #[clap(
+        short = 'F',
+        long = "forbidden",
+        help = "check that founders do not recombine at nodes listed in file (node id or range \"a-b\" per line)"
+    )]
+    pub forbidden: Option<String>,
+
+    #[clap(
+        short = 'g',
+        long = "graph",
+        help = "report founders by the segment names of given graph (GFA1 format), required if these are not numeric"
+    )]
+    pub graph: Option<String>,
+
+    #[clap(
+        short = 'B',
+        long = "breakpoints",
+        help = "write recombination breakpoints, annotated by the innermost superbubble of the graph containing them and the superbubbles they enter or exit, to file",
+        requires = "graph"
+    )]
+    pub breakpoints: Option<String>,
+
+    #[clap(
+        short = 'L',
+        long = "solver-log",
+        help = "check solver status in given Gurobi log file and fail if no (feasible) solution was found"
+    )]
+    pub solver_log: Option<String>,
+
     #[clap(help = "ilp solution", required = true)]
     pub sol: String,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
 }
 
 fn parse_founder_sequences<R: BufRead>(
@@ -200,7 +235,7 @@ fn walk_sol(
 }
 
 fn write_founders_compact<W: io::Write>(
-    fs: Vec<Vec<(u64, bool, bool, usize)>>,
+    fs: Vec<Vec<(String, bool, bool, usize)>>,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     log::info!("writing final haplotype-minimized founders");
@@ -255,16 +290,18 @@ fn write_founders_compact<W: io::Write>(
     })
 }
 
-fn read_haplotypes(file: String) -> Vec<String> {
-    let f = fs::File::open(file).expect("can't open haplotype file");
-    BufReader::new(f)
-        .lines()
-        .map(|l| l.unwrap().split('\t').nth(0).unwrap().to_string())
+// haplotypes are enumerated by fragments, as they are in mkmin
+fn read_haplotypes(file: String, names: &ff::SegmentNames) -> Vec<String> {
+    let f = ff::open_file(&file).expect("can't open haplotype file");
+    ff::read_haplotype_fragments(f, names)
+        .unwrap()
+        .into_iter()
+        .map(|(name, _)| name)
         .collect::<Vec<_>>()
 }
 
 fn write_founders_long<W: io::Write>(
-    fs: Vec<Vec<(u64, bool, bool, usize)>>,
+    fs: Vec<Vec<(String, bool, bool, usize)>>,
     hmap: FxHashMap<usize, String>,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
@@ -309,7 +346,7 @@ fn write_founders_long<W: io::Write>(
 }
 
 fn write_founders_wide<W: io::Write>(
-    fs: Vec<Vec<(u64, bool, bool, usize)>>,
+    fs: Vec<Vec<(String, bool, bool, usize)>>,
     hmap: FxHashMap<usize, String>,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
@@ -356,26 +393,211 @@ fn write_founders_wide<W: io::Write>(
         })
 }
 
+fn infeasible_error(sol: &str, forbidden: &Option<String>) -> io::Error {
+    let msg = match forbidden {
+        None => format!("{} contains no solution, minimization problem is infeasible", sol),
+        Some(f) => format!(
+            "{} contains no solution, minimization problem is infeasible, possibly due to forbidden recombinations at nodes listed in {}",
+            sol, f
+        ),
+    };
+    log::error!("{}", msg);
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+// the objective value reported in the header of a solution is not finite if the program is
+// infeasible or unbounded
+fn check_objective(sol: &[u8], file: &str, forbidden: &Option<String>) -> Result<(), io::Error> {
+    for line in sol.lines().map_while(Result::ok) {
+        if let Some(x) = line.strip_prefix("# Objective value = ") {
+            match f64::from_str(x.trim()) {
+                Ok(x) if x.is_finite() => (),
+                _ => return Err(infeasible_error(file, forbidden)),
+            }
+        }
+        if !line.starts_with('#') {
+            break;
+        }
+    }
+    Ok(())
+}
+
+fn check_solver_log(
+    log_file: &str,
+    sol: &str,
+    forbidden: &Option<String>,
+) -> Result<(), io::Error> {
+    log::info!("checking solver status in {}", log_file);
+    for line in ff::open_file(log_file)?.lines() {
+        let line = line?;
+        if line.starts_with("Model is infeasible")
+            || line.starts_with("Infeasible model")
+            || line.starts_with("Infeasible or unbounded model")
+        {
+            return Err(infeasible_error(sol, forbidden));
+        } else if line.starts_with("Solution count 0") {
+            let msg = format!("solver found no solution for {}, see {}", sol, log_file);
+            log::error!("{}", msg);
+            return Err(io::Error::new(io::ErrorKind::InvalidData, msg));
+        } else if line.starts_with("Time limit reached") {
+            log::warn!(
+                "solver reached time limit, solution {} is not necessarily optimal",
+                sol
+            );
+        }
+    }
+    Ok(())
+}
+
+fn check_forbidden_recombinations(
+    fs: &[Vec<(u64, bool, bool, usize)>],
+    forbidden: &FxHashSet<u64>,
+    names: &ff::SegmentNames,
+) -> Result<(), io::Error> {
+    log::info!("checking for recombinations at forbidden nodes");
+    let mut violations = 0;
+    fs.iter().enumerate().for_each(|(i, f)| {
+        f.iter()
+            .skip(1)
+            .filter(|(u, _, s, _)| *s && forbidden.contains(u))
+            .for_each(|(u, _, _, _)| {
+                log::error!(
+                    "founder_seq{} recombines at forbidden node {}",
+                    i + 1,
+                    names.name(*u as usize)
+                );
+                violations += 1;
+            })
+    });
+    if violations > 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "founders recombine {} time(s) at forbidden nodes",
+                violations
+            ),
+        ));
+    }
+    Ok(())
+}
+
+// bubbles are numbered as in the output of the bubbles tool
+fn write_breakpoints<W: io::Write>(
+    fs: &[Vec<(u64, bool, bool, usize)>],
+    bubbles: &ff::BubbleDecomposition,
+    names: &ff::SegmentNames,
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
+    log::info!("writing recombination breakpoints");
+    writeln!(
+        out,
+        "founder\tposition\tnode\tbubble\tentry\texit\tflanking\tcut_node"
+    )?;
+    for (i, f) in fs.iter().enumerate() {
+        for (j, (u, d, _, _)) in f.iter().enumerate().skip(1).filter(|(_, x)| x.2) {
+            let b = bubbles.bubble_of(*u);
+            writeln!(
+                out,
+                "founder_seq{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}",
+                i + 1,
+                j + 1,
+                names.v2str(&Handle::pack(*u, *d)),
+                b.map(|x| (x + 1).to_string())
+                    .unwrap_or_else(|| String::from("*")),
+                b.map(|x| names.v2str(&bubbles.bubbles[x].entry))
+                    .unwrap_or_else(|| String::from("*")),
+                b.map(|x| names.v2str(&bubbles.bubbles[x].exit))
+                    .unwrap_or_else(|| String::from("*")),
+                match bubbles.bubbles_bounded_by(*u) {
+                    [] => String::from("*"),
+                    xs => xs.iter().map(|x| (x + 1).to_string()).join(","),
+                },
+                bubbles.is_cut_node(*u)
+            )?;
+        }
+    }
+    Ok(())
+}
+
 fn main() -> Result<(), io::Error> {
     env_logger::init();
     let params = Args::parse();
 
-    let f = fs::File::open(&params.sol).expect("can't open sol file");
-    let mut sol = BufReader::new(f);
+    let mut bubbles = None;
+    let names = match &params.graph {
+        None => ff::SegmentNames::default(),
+        Some(g) if params.breakpoints.is_some() => {
+            log::info!("loading graph {}", g);
+            let ff::StreamedGFA { graph, names, .. } =
+                ff::read_gfa_file(g, &ff::GFAReadOptions::default())?;
+            log::info!("decomposing graph into superbubbles");
+            bubbles = Some(ff::decompose_bubbles(&graph));
+            names
+        }
+        Some(g) => {
+            log::info!("reading segment names from graph {}", g);
+            ff::read_segment_names(g)?
+        }
+    };
+
+    let forbidden = match &params.forbidden {
+        None => FxHashSet::default(),
+        Some(f) => ff::read_node_ranges(ff::open_file(f)?, &names).unwrap(),
+    };
+
+    // solvers do not write a solution (or write an empty one) if the program is infeasible
+    let mut f = match ff::open_file(&params.sol) {
+        Ok(f) => f,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            return Err(infeasible_error(&params.sol, &params.forbidden))
+        }
+        Err(e) => return Err(e),
+    };
+    // compressed solutions cannot be rewound, so the solution is kept in memory
+    let mut buf: Vec<u8> = Vec::new();
+    f.read_to_end(&mut buf)?;
+    if let Some(l) = &params.solver_log {
+        check_solver_log(l, &params.sol, &params.forbidden)?;
+    }
+    check_objective(&buf, &params.sol, &params.forbidden)?;
+    let mut sol = io::Cursor::new(buf);
     let g = parse_founder_sequences(&mut sol)?;
+    if g.node_count() == 0 {
+        return Err(infeasible_error(&params.sol, &params.forbidden));
+    }
     sol.rewind()?;
 
     let (cols, switch) = parse_haplotype_assignments(&mut sol, &g)?;
     let fs = walk_sol(g, cols, switch);
+    if !forbidden.is_empty() {
+        check_forbidden_recombinations(&fs, &forbidden, &names)?;
+    }
+    if let (Some(file), Some(bubbles)) = (&params.breakpoints, &bubbles) {
+        let mut b = ff::create_file(file)?;
+        write_breakpoints(&fs, bubbles, &names, &mut b)?;
+        ff::finish(b)?;
+    }
+    let fs: Vec<Vec<(String, bool, bool, usize)>> = fs
+        .into_iter()
+        .map(|f| {
+            f.into_iter()
+                .map(|(u, d, s, c)| (names.name(u as usize), d, s, c))
+                .collect()
+        })
+        .collect();
 
     let mut hmap = FxHashMap::default();
     if let Some(hf) = params.haps {
-        read_haplotypes(hf).iter().enumerate().for_each(|(i, x)| {
-            hmap.insert(i, x.to_string());
-        });
+        // haplotype identifiers of the minimization program start at 1
+        read_haplotypes(hf, &names)
+            .iter()
+            .enumerate()
+            .for_each(|(i, x)| {
+                hmap.insert(i + 1, x.to_string());
+            });
     }
 
-    let mut out = io::BufWriter::new(std::io::stdout());
+    let mut out = ff::compress(io::stdout(), params.compress)?;
     if params.long {
         write_founders_long(fs, hmap, &mut out)?;
     } else if params.compact {
@@ -389,7 +611,7 @@ fn main() -> Result<(), io::Error> {
     } else {
         write_founders_wide(fs, hmap, &mut out)?;
     }
-    out.flush()?;
+    ff::finish(out)?;
 
     log::info!("done");
     Ok(())
@@ -1,15 +1,9 @@
 /* standard use */
-use std::cmp::min;
-use std::fs;
-use std::io::{self, BufRead, Write};
-use std::iter::FromIterator;
+use std::io::{self, Write};
 
 /* crate use */
 use clap::Parser;
-use handlegraph::handle::Handle;
 use itertools::Itertools;
-use rand::distributions::{Distribution, Uniform};
-use rustc_hash::{FxHashMap, FxHashSet};
 
 /* private use */
 use founderset as ff;
@@ -27,6 +21,13 @@ pub struct Command {
     #[clap(index = 2, required = true, help = "haplotype sequences")]
     pub haplotype_set: String,
 
+    #[clap(
+        short = 'g',
+        long = "graph",
+        help = "Graph (GFA1 format) whose segment names are used in founder and haplotype sequences, required if these are not numeric"
+    )]
+    pub graph: Option<String>,
+
     #[clap(
         short = 'n',
         long = "repeats",
@@ -34,104 +35,19 @@ pub struct Command {
         default_value = "10000"
     )]
     pub repeats: usize,
-}
-
-fn read_founderseqs<R: io::Read>(
-    data: io::BufReader<R>,
-) -> Result<Vec<(String, Vec<Handle>)>, String> {
-    let mut seqs: Vec<(String, Vec<Handle>)> = Vec::new();
-
-    for line_op in data.lines() {
-        if let Ok(line) = line_op {
-            seqs.push((
-                line[..line.find('\t').unwrap()].to_string(),
-                ff::parse_walk(&line)?,
-            ));
-        }
-    }
-    Ok(seqs)
-}
-
-fn haplotype_to_adj_map(
-    haps: &Vec<(String, Vec<Handle>)>,
-) -> FxHashMap<(Handle, Handle), FxHashSet<(usize, usize, bool)>> {
-    let mut res: FxHashMap<(Handle, Handle), FxHashSet<(usize, usize, bool)>> =
-        FxHashMap::default();
-    for (x, (_, s)) in haps.into_iter().enumerate() {
-        let n = s.len() - 1;
-        s.into_iter()
-            .tuple_windows()
-            .enumerate()
-            .for_each(|(i, (u, v))| {
-                res.entry((v.flip(), u.flip()))
-                    .or_insert(FxHashSet::default())
-                    .insert((x, n - i, true));
-                res.entry((*u, *v))
-                    .or_insert(FxHashSet::default())
-                    .insert((x, i, false));
-            })
-    }
-    res
-}
 
-fn read_haplotypes<R: io::Read>(
-    data: io::BufReader<R>,
-) -> Result<Vec<(String, Vec<Handle>)>, String> {
-    let mut res: Vec<(String, Vec<Handle>)> = Vec::new();
-
-    for line_op in data.lines() {
-        if let Ok(line) = line_op {
-            res.push((
-                line[..line.find('\t').unwrap()].to_string(),
-                ff::parse_walk(&line)?,
-            ));
-        }
-    }
-
-    Ok(res)
-}
+    #[clap(
+        short = 'M',
+        long = "mismatch-penalty",
+        help = "Allow founder adjacencies that are not traversed by any haplotype at given penalty each"
+    )]
+    pub mismatch_penalty: Option<usize>,
 
-fn color_and_count(
-    seq: &Vec<Handle>,
-    adjs: &FxHashMap<(Handle, Handle), FxHashSet<(usize, usize, bool)>>,
-    repeats: usize,
-) -> usize {
-    let mut rng = rand::thread_rng();
-
-    // construct data structure for efficient access for random selection
-    let adjs_vec: FxHashMap<(Handle, Handle), (Vec<(usize, usize, bool)>, Uniform<_>)> =
-        FxHashMap::from_iter(adjs.iter().map(|((u, v), s)| {
-            (
-                (*u, *v),
-                (s.iter().cloned().collect(), Uniform::from(0..s.len())),
-            )
-        }));
-
-    let mut cur_min = usize::MAX;
-    for _ in 0..repeats {
-        let mut c = 0;
-        let mut cur: Option<(usize, usize, bool)> = None;
-        for (&u, &v) in seq.into_iter().tuple_windows() {
-            let (vs, r) = adjs_vec.get(&(u, v)).expect(&format!(
-                "oops, adjacency {}{} not contained in map, string is not colorable!",
-                ff::v2str(&u),
-                ff::v2str(&v)
-            ));
-            cur = Some(match cur {
-                None => vs[r.sample(&mut rng)],
-                Some((x, i, o)) => {
-                    if vs.contains(&(x, i + 1, o)) {
-                        (x, i + 1, o)
-                    } else {
-                        c += 1;
-                        vs[r.sample(&mut rng)]
-                    }
-                }
-            });
-        }
-        cur_min = min(cur_min, c);
-    }
-    cur_min
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
 }
 
 fn main() -> Result<(), std::io::Error> {
@@ -144,16 +60,24 @@ fn main() -> Result<(), std::io::Error> {
         params.repeats
     );
 
+    let names = match &params.graph {
+        None => ff::SegmentNames::default(),
+        Some(g) => {
+            log::info!("reading segment names from graph {}", g);
+            ff::read_segment_names(g)?
+        }
+    };
+
     log::info!("loading founder sequences from {}", params.founder_set);
-    let founder_data = io::BufReader::new(fs::File::open(&params.founder_set)?);
-    let founder_seqs = read_founderseqs(founder_data).unwrap();
+    let founder_data = ff::open_file(&params.founder_set)?;
+    let founder_seqs = ff::read_walks(founder_data, &names).unwrap();
     log::info!("parsed {} founders", founder_seqs.len());
 
     log::info!("loading haplotype sequences from {}", params.haplotype_set);
-    let hap_data = io::BufReader::new(fs::File::open(&params.haplotype_set)?);
-    let haplotypes = read_haplotypes(hap_data).unwrap();
-    log::info!("parsed {} haplotypes", haplotypes.len());
-    let hap_adjs = haplotype_to_adj_map(&haplotypes);
+    let hap_data = ff::open_file(&params.haplotype_set)?;
+    let haplotypes = ff::read_haplotype_fragments(hap_data, &names).unwrap();
+    log::info!("parsed {} haplotype fragments", haplotypes.len());
+    let hap_adjs = ff::haplotype_to_adj_map(&haplotypes);
     log::debug!(
         "haplotype adjacency map: {}",
         hap_adjs
@@ -170,17 +94,17 @@ fn main() -> Result<(), std::io::Error> {
             .join("\n")
     );
 
-    let mut out = io::BufWriter::new(std::io::stdout());
+    let mut out = ff::compress(io::stdout(), params.compress)?;
 
     let mut c = 0;
     for (name, s) in founder_seqs.iter() {
-        let sc = color_and_count(s, &hap_adjs, params.repeats.clone());
+        let sc = ff::color_and_count(s, &hap_adjs, params.repeats, params.mismatch_penalty);
         log::info!("{} has {} recombinations", name, &sc);
         c += sc;
     }
     log::info!("total #recombinations: {}", c);
     writeln!(out, "{}", c)?;
-    out.flush()?;
+    ff::finish(out)?;
 
     log::info!("done");
     Ok(())
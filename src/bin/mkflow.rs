@@ -1,16 +1,16 @@
 /* standard use */
+use std::cmp::min;
 use std::io;
 use std::io::prelude::*;
 
 /* crate use */
 use clap::Parser;
-use gfa::{gfa::GFA, parser::GFAParser};
 use handlegraph::{
-    handle::{Direction, Edge, Handle},
+    handle::{Direction, Edge},
     handlegraph::*,
     hashgraph::HashGraph,
 };
-use rustc_hash::FxHashSet;
+use rustc_hash::FxHashMap;
 
 /* private use */
 use founderset as ff;
@@ -29,28 +29,32 @@ pub struct Command {
     )]
     nfounder: Option<usize>,
 
+    #[clap(
+        short = 'w',
+        long = "weighted",
+        help = "Weight objective by segment length (sequence or LN tag) to minimize total founder sequence length"
+    )]
+    weighted: bool,
+
     #[clap(index = 1, help = "graph in GFA1 format", required = true)]
     pub graph: String,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
 }
 
 fn write_lp<W: io::Write>(
     graph: &HashGraph,
     nfounder: Option<usize>,
+    seglen: Option<&FxHashMap<usize, usize>>,
+    overlaps: &ff::LinkOverlaps,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     // find sources & sinks
-    //
-    // - sources are all nodes that in default orientation have no edges to their left
-    // - sinks are all nodes that in default orientation have no edges to their right
-    let mut sources: FxHashSet<Handle> = FxHashSet::default();
-    let mut sinks: Vec<Handle> = Vec::new();
-    for v in graph.handles() {
-        if graph.degree(v, Direction::Right) == 0 {
-            sinks.push(v);
-        } else if graph.degree(v, Direction::Left) == 0 {
-            sources.insert(v);
-        }
-    }
+    let (sources, sinks) = ff::graph_sources_sinks(graph);
 
     log::info!(
         "identified {} sources: {}\nidentified {} sinks: {}",
@@ -62,27 +66,74 @@ fn write_lp<W: io::Write>(
 
     // write objective
     writeln!(out, "Minimize")?;
-    writeln!(
-        out,
-        "{}",
-        graph
-            .edges()
-            .map(|Edge(u, v)| {
-                if u == v.flip() {
-                    format!("o{}_i{}", ff::v2extstr(&u), ff::v2extstr(&v.flip()))
-                } else {
+    if let Some(lens) = seglen {
+        // each traversal of a segment enters exactly one of its extremities, hence the in-flow of
+        // all extremities weighted by segment length sums up to the total length of all founders
+        writeln!(
+            out,
+            "{}",
+            graph
+                .handles()
+                .map(|v| {
+                    let l = lens.get(&(v.unpack_number() as usize)).unwrap_or(&0);
                     format!(
-                        "o{}_i{} + o{}_i{}",
-                        ff::v2extstr(&u),
-                        ff::v2extstr(&v.flip()),
-                        ff::v2extstr(&v.flip()),
-                        ff::v2extstr(&u)
+                        "{} i{} + {} i{}",
+                        l,
+                        ff::v2extstr(&v),
+                        l,
+                        ff::v2extstr(&v.flip())
                     )
+                })
+                .collect::<Vec<String>>()
+                .join(" + ")
+        )?;
+        // overlapping sequence is spelled only once when traversing a link; overlaps are truncated
+        // to the length of the segment left, so that no traversal (and hence no cycle) has
+        // negative length
+        for Edge(u, v) in graph.edges() {
+            let mut traversals = vec![(u, v)];
+            if u != v.flip() {
+                traversals.push((v.flip(), u.flip()));
+            }
+            for (x, y) in traversals {
+                let l = min(
+                    ff::overlap_length(&ff::link_overlap(overlaps, &x, &y)),
+                    *lens.get(&(x.unpack_number() as usize)).unwrap_or(&0),
+                );
+                if l > 0 {
+                    writeln!(
+                        out,
+                        " - {} o{}_i{}",
+                        l,
+                        ff::v2extstr(&x),
+                        ff::v2extstr(&y.flip())
+                    )?;
                 }
-            })
-            .collect::<Vec<String>>()
-            .join(" + ")
-    )?;
+            }
+        }
+    } else {
+        writeln!(
+            out,
+            "{}",
+            graph
+                .edges()
+                .map(|Edge(u, v)| {
+                    if u == v.flip() {
+                        format!("o{}_i{}", ff::v2extstr(&u), ff::v2extstr(&v.flip()))
+                    } else {
+                        format!(
+                            "o{}_i{} + o{}_i{}",
+                            ff::v2extstr(&u),
+                            ff::v2extstr(&v.flip()),
+                            ff::v2extstr(&v.flip()),
+                            ff::v2extstr(&u)
+                        )
+                    }
+                })
+                .collect::<Vec<String>>()
+                .join(" + ")
+        )?;
+    }
 
     writeln!(out, "Subject To")?;
 
@@ -264,22 +315,68 @@ fn write_lp<W: io::Write>(
 fn main() -> Result<(), io::Error> {
     env_logger::init();
 
-    // print output to stdout
-    let mut out = io::BufWriter::new(std::io::stdout());
-
     // initialize command line parser & parse command line arguments
     let params = Command::parse();
 
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+
     log::info!("loading graph {}", &params.graph);
-    let parser = GFAParser::new();
-    let gfa: GFA<usize, ()> = parser.parse_file(&params.graph).unwrap();
+    let ff::StreamedGFA {
+        graph,
+        lengths,
+        annotations,
+        ..
+    } = ff::read_gfa_file(
+        &params.graph,
+        &ff::GFAReadOptions {
+            lengths: params.weighted,
+            ..Default::default()
+        },
+    )?;
 
-    log::info!("constructing handle graph");
-    let graph = HashGraph::from_gfa(&gfa);
+    let seglen = if params.weighted {
+        log::info!("weighting objective by segment lengths");
+        // segments without sequence and LN tag have length 0 and do not count towards the objective
+        let unknown = graph
+            .handles()
+            .filter(|v| {
+                lengths
+                    .get(&(v.unpack_number() as usize))
+                    .copied()
+                    .unwrap_or(0)
+                    == 0
+            })
+            .count();
+        if unknown == graph.node_count() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "segments of graph {} have neither sequence nor LN tag, cannot weight objective by segment length",
+                    params.graph
+                ),
+            ));
+        } else if unknown > 0 {
+            log::warn!(
+                "{} of {} segments have neither sequence nor LN tag and are weighted 0",
+                unknown,
+                graph.node_count()
+            );
+        }
+        Some(lengths)
+    } else {
+        None
+    };
 
     log::info!("writing linear program");
-    write_lp(&graph, params.nfounder, &mut out)?;
-    out.flush()?;
+    write_lp(
+        &graph,
+        params.nfounder,
+        seglen.as_ref(),
+        &annotations.overlaps,
+        &mut out,
+    )?;
+    ff::finish(out)?;
 
     log::info!("done");
     Ok(())
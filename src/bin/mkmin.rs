@@ -1,12 +1,12 @@
 /* standard use */
 use std::cmp::min;
-use std::fs;
 use std::io::{self, BufRead, Write};
 use std::iter::FromIterator;
+use std::str::FromStr;
 
 /* crate use */
 use clap::Parser;
-use handlegraph::handle::Handle;
+use handlegraph::handle::{Edge, Handle};
 use itertools::Itertools;
 use petgraph::{graphmap::DiGraphMap, Incoming, Outgoing};
 use rustc_hash::{FxHashMap, FxHashSet};
@@ -31,6 +31,158 @@ pub struct Command {
 
     #[clap(index = 2, required = true, help = "haplotype sequences")]
     pub haplotype_set: String,
+
+    #[clap(
+        short = 'g',
+        long = "graph",
+        help = "Graph (GFA1 format) whose segment names are used in founder and haplotype sequences, required if these are not numeric"
+    )]
+    pub graph: Option<String>,
+
+    #[clap(
+        short = 'r',
+        long = "recomb-costs",
+        help = "Per-node or per-adjacency recombination costs (tab-separated node id or two-step walk, e.g. \">1>2\", and cost, default cost is 1)"
+    )]
+    pub recomb_costs: Option<String>,
+
+    #[clap(
+        long = "cost-tag",
+        help = "Read per-node and per-adjacency recombination costs from given tag (e.g. \"RR\" for RR:f:<cost>) of segments and links of the graph, costs in file take precedence",
+        requires = "graph"
+    )]
+    pub cost_tag: Option<String>,
+
+    #[clap(
+        short = 'F',
+        long = "forbidden",
+        help = "Nodes at which founders must not recombine (one node id or range \"a-b\" per line)"
+    )]
+    pub forbidden: Option<String>,
+
+    #[clap(
+        short = 'm',
+        long = "minmax",
+        help = "Minimize the maximum number of recombinations on any single founder",
+        conflicts_with = "weighted"
+    )]
+    pub minmax: bool,
+
+    #[clap(
+        short = 'j',
+        long = "joint",
+        help = "Let founder adjacencies vary while keeping the number of founders fixed"
+    )]
+    pub joint: bool,
+
+    #[clap(
+        short = 'M',
+        long = "mismatch-penalty",
+        help = "Allow founder adjacencies that are not traversed by any haplotype, and haplotype adjacencies that are not traversed by any founder, at given penalty each"
+    )]
+    pub mismatch_penalty: Option<usize>,
+
+    #[clap(
+        short = 'w',
+        long = "weighted",
+        help = "Scale adjacency multiplicities and recombination costs by haplotype weights (third column of haplotype file)"
+    )]
+    pub weighted: bool,
+
+    #[clap(
+        short = 'P',
+        long = "phase-switch-cost",
+        help = "Group haplotypes by sample (PanSN names \"sample#hap#contig\") and charge switches between haplotypes of the same sample with given cost relative to a recombination (between 0 and 1)",
+        parse(try_from_str = parse_relative_cost)
+    )]
+    pub phase_switch_cost: Option<f64>,
+
+    #[clap(
+        short = 'D',
+        long = "dump-graph",
+        help = "Write flow graph to <PREFIX>.dot and <PREFIX>.graphml for inspection"
+    )]
+    pub dump_graph: Option<String>,
+
+    #[clap(
+        short = 'S',
+        long = "solution",
+        help = "Highlight matchings and colors of the given solution (Gurobi format) in the dumped flow graph",
+        requires = "dump-graph"
+    )]
+    pub solution: Option<String>,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+// recombination costs of nodes and adjacencies; a recombination at a node traversal is charged the
+// cost of the adjacency through which the founder enters the node, if given, and that of the node
+// otherwise
+#[derive(Default)]
+pub struct RecombinationCosts {
+    pub nodes: FxHashMap<u64, f64>,
+    // given in both orientations
+    pub adjs: FxHashMap<(Handle, Handle), f64>,
+}
+
+impl RecombinationCosts {
+    fn insert_adj(&mut self, u: Handle, v: Handle, c: f64) {
+        self.adjs.insert((u, v), c);
+        self.adjs.insert((v.flip(), u.flip()), c);
+    }
+
+    // cost of each incoming node of the flow graph, whose predecessor along the "solid" edge
+    // identifies the adjacency through which the founder enters
+    fn of_in_nodes(&self, g: &DiGraphMap<ff::Node, ff::EdgeType>) -> FxHashMap<ff::Node, f64> {
+        g.nodes()
+            .filter(|u| u.direction == ff::Direction::In)
+            .map(|u| {
+                let v = Handle::pack(u.node, u.etype == ff::ExtremityType::Head);
+                let c = g
+                    .neighbors_directed(u, Incoming)
+                    .next()
+                    .and_then(|p| {
+                        let p = Handle::pack(p.node, p.etype == ff::ExtremityType::Tail);
+                        self.adjs.get(&(p, v))
+                    })
+                    .or_else(|| self.nodes.get(&u.node))
+                    .copied()
+                    .unwrap_or(1.0);
+                (u, c)
+            })
+            .collect()
+    }
+}
+
+// optional extensions of the recombination minimization model
+pub struct ModelOptions {
+    pub costs: Option<RecombinationCosts>,
+    pub forbidden: FxHashSet<u64>,
+    pub minmax: bool,
+    // number of founders, if founder adjacencies are subject to optimization
+    pub joint: Option<usize>,
+    // penalty for traversing a founder adjacency that is not supported by any haplotype
+    pub mismatch: Option<usize>,
+    // weight of each haplotype (fragment) relative to the largest one; a recombination is charged
+    // in proportion to the weight of the haplotype that is left
+    pub weights: Option<Vec<f64>>,
+    // cost of switching between haplotypes of the same sample relative to a recombination, along
+    // with the sample of each haplotype (fragment)
+    pub phase_switch: Option<(f64, Vec<String>)>,
+}
+
+// founder adjacencies ("solid" edges) not traversed by any haplotype and the node traversals
+// ("dashed" edges) next to them, which are charged with the mismatch penalty instead of counting as
+// recombinations
+#[derive(Default)]
+pub struct Mismatches {
+    pub penalty: usize,
+    pub adjs: Vec<(ff::Node, ff::Node)>,
+    pub exempt: FxHashSet<(ff::Node, ff::Node)>,
 }
 
 /* https://www.gurobi.com/documentation/9.5/refman/lp_format.html
@@ -40,19 +192,55 @@ pub struct Command {
  * - no strict LT/GT: <, <= and resp. >, >= are strictly equivalent
  */
 
+// haplotype and color identifier of color conservation variable t_{u}_{w}_{h}_{i}
+fn color_of(t: &str) -> (usize, usize) {
+    let mut it = t.rsplit('_').map(|x| usize::from_str(x).unwrap());
+    let i = it.next().unwrap();
+    (it.next().unwrap(), i)
+}
+
 fn write_obj<W: io::Write>(
-    color_conservation_vars: &FxHashSet<String>,
+    color_conservation_vars: &FxHashMap<String, (ff::Node, ff::Node)>,
+    costs: &Option<FxHashMap<ff::Node, f64>>,
+    recombination_vars: Option<&[(String, f64)]>,
+    mismatches: &Mismatches,
+    phase_switch_vars: &[(String, f64)],
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
-    writeln!(
-        out,
-        "T - {}",
-        color_conservation_vars
-            .into_iter()
-            .cloned()
-            .collect::<Vec<String>>()
-            .join(" - ")
-    )
+    let weighted = |v: &ff::Node, var: String| match costs {
+        None => var,
+        Some(c) => format!("{} {}", c.get(v).unwrap_or(&1.0), var),
+    };
+    match recombination_vars {
+        Some(rs) => write!(
+            out,
+            "{}",
+            rs.iter().map(|(r, c)| format!("{} {}", c, r)).join(" + ")
+        )?,
+        None => write!(
+            out,
+            "T - {}",
+            color_conservation_vars
+                .iter()
+                .filter(|(_, e)| !mismatches.exempt.contains(e))
+                .map(|(t, (v, _))| weighted(v, t.clone()))
+                .chain(
+                    mismatches
+                        .exempt
+                        .iter()
+                        .map(|(u, w)| weighted(u, format!("x_{}_{}", u, w)))
+                )
+                .collect::<Vec<String>>()
+                .join(" - ")
+        )?,
+    }
+    for (u, v) in mismatches.adjs.iter() {
+        write!(out, " + {} x_{}_{}", mismatches.penalty, u, v)?;
+    }
+    for (p, c) in phase_switch_vars.iter() {
+        write!(out, " - {} {}", c, p)?;
+    }
+    writeln!(out)
 }
 
 fn write_con_match<W: io::Write>(
@@ -178,7 +366,7 @@ fn write_con_color_contiguity<W: io::Write>(
     haplotype: &Vec<Handle>,
     haplotype_id: usize,
     color_vars: &mut FxHashMap<ff::Node, FxHashSet<(usize, usize)>>,
-    color_conservation_vars: &mut FxHashSet<String>,
+    color_conservation_vars: &mut FxHashMap<String, (ff::Node, ff::Node)>,
     vars: &mut [FxHashSet<String>],
     pick_up_nodes: bool,
     out: &mut io::BufWriter<W>,
@@ -257,7 +445,7 @@ fn write_con_color_contiguity<W: io::Write>(
                         .entry(v)
                         .or_insert(FxHashSet::default())
                         .insert((haplotype_id, j));
-                    color_conservation_vars.insert(t);
+                    color_conservation_vars.insert(t, (*u, w));
                     next_nodes.insert(v);
                 }
             }
@@ -265,9 +453,14 @@ fn write_con_color_contiguity<W: io::Write>(
         cur_nodes = next_nodes;
     }
 
+    // the color of the last node is passed on only to outgoing nodes without further adjacency, so
+    // that founders cannot switch haplotypes for free where a haplotype fragment ends
     let i = haplotype.len() - 1;
     for v in cur_nodes.iter() {
-        for w in graph.neighbors(*v) {
+        for w in graph
+            .neighbors(*v)
+            .filter(|w| graph.neighbors(*w).next().is_none())
+        {
             let t = format!("t_{}_{}_{}_{}", v, w, haplotype_id, i);
             let x = format!("x_{}_{}", v, w);
             let c1 = format!("c_{}_{}_{}", v, haplotype_id, i);
@@ -284,10 +477,242 @@ fn write_con_color_contiguity<W: io::Write>(
                 .entry(w)
                 .or_insert(FxHashSet::default())
                 .insert((haplotype_id, i));
-            color_conservation_vars.insert(t);
+            color_conservation_vars.insert(t, (*v, w));
+        }
+    }
+
+    Ok(())
+}
+
+// recombination variable r_u_h_i indicates that the founder traversing incoming node u leaves
+// color i of haplotype h at u, i.e., u is traversed and colored by (h, i), but the color is not
+// conserved along the "dashed" edge, nor is the traversal exempt due to a mismatch; returns the
+// recombination variables along with their objective coefficients, i.e., the recombination cost
+// of u scaled by the weight of h
+fn write_con_weighted_recombination<W: io::Write>(
+    g: &DiGraphMap<ff::Node, ff::EdgeType>,
+    color_vars: &FxHashMap<ff::Node, FxHashSet<(usize, usize)>>,
+    color_conservation_vars: &FxHashMap<String, (ff::Node, ff::Node)>,
+    cost: &dyn Fn(&ff::Node, usize) -> f64,
+    mismatches: &Mismatches,
+    vars: &mut [FxHashSet<String>],
+    out: &mut io::BufWriter<W>,
+) -> Result<Vec<(String, f64)>, io::Error> {
+    writeln!(out, "\\\n\\ weighted recombination constraints \n\\")?;
+
+    let mut node2t: FxHashMap<_, Vec<&String>> = FxHashMap::default();
+    color_conservation_vars
+        .iter()
+        .filter(|(_, e)| !mismatches.exempt.contains(e))
+        .for_each(|(t, (u, _))| {
+            let (h, i) = color_of(t);
+            node2t.entry((*u, h, i)).or_default().push(t)
+        });
+    let mut exempt: FxHashMap<ff::Node, Vec<String>> = FxHashMap::default();
+    mismatches
+        .exempt
+        .iter()
+        .for_each(|(u, w)| exempt.entry(*u).or_default().push(format!("x_{}_{}", u, w)));
+
+    let mut res: Vec<(String, f64)> = Vec::new();
+    for u in g.nodes().filter(|u| u.direction == ff::Direction::In) {
+        for (h, i) in color_vars.get(&u).into_iter().flatten().sorted() {
+            let r = format!("r_{}_{}_{}", u, h, i);
+            write!(out, "{} - c_{}_{}_{} - y_{}", r, u, h, i, u)?;
+            for x in node2t
+                .get(&(u, *h, *i))
+                .into_iter()
+                .flatten()
+                .map(|t| t.as_str())
+                .chain(exempt.get(&u).into_iter().flatten().map(|x| x.as_str()))
+            {
+                write!(out, " + {}", x)?;
+            }
+            writeln!(out, " >= -1")?;
+            vars[VBIN].insert(r.clone());
+            res.push((r, cost(&u, *h)));
+        }
+    }
+    Ok(res)
+}
+
+// phase switch variable p_u_w_h_k indicates that "dashed" edge (u, w) is used in the matching and
+// that u is colored by haplotype h and w by haplotype k of the same sample; returns the phase
+// switch variables along with their objective coefficients
+fn write_con_phase_switch<W: io::Write>(
+    g: &DiGraphMap<ff::Node, ff::EdgeType>,
+    color_vars: &FxHashMap<ff::Node, FxHashSet<(usize, usize)>>,
+    (phase_cost, samples): (f64, &[String]),
+    cost: &dyn Fn(&ff::Node, usize) -> f64,
+    mismatches: &Mismatches,
+    vars: &mut [FxHashSet<String>],
+    out: &mut io::BufWriter<W>,
+) -> Result<Vec<(String, f64)>, io::Error> {
+    writeln!(out, "\\\n\\ phase switch constraints \n\\")?;
+
+    // colors of each node, grouped by haplotype
+    let haps_of = |v: &ff::Node| -> FxHashMap<usize, Vec<usize>> {
+        let mut res: FxHashMap<usize, Vec<usize>> = FxHashMap::default();
+        if let Some(cs) = color_vars.get(v) {
+            cs.iter()
+                .for_each(|(h, i)| res.entry(*h).or_default().push(*i));
+        }
+        res
+    };
+
+    let mut res: Vec<(String, f64)> = Vec::new();
+    for (u, w, _) in g
+        .all_edges()
+        .filter(|(u, w, t)| **t == ff::EdgeType::Dashed && !mismatches.exempt.contains(&(*u, *w)))
+    {
+        let hu = haps_of(&u);
+        let hw = haps_of(&w);
+        for (h, is) in hu.iter() {
+            for (k, js) in hw
+                .iter()
+                .filter(|(k, _)| *k != h && samples[**k - 1] == samples[*h - 1])
+            {
+                let p = format!("p_{}_{}_{}_{}", u, w, h, k);
+                writeln!(
+                    out,
+                    "3 {} - x_{}_{} - {} - {} <= 0",
+                    p,
+                    u,
+                    w,
+                    is.iter()
+                        .map(|i| format!("c_{}_{}_{}", u, h, i))
+                        .join(" - "),
+                    js.iter()
+                        .map(|j| format!("c_{}_{}_{}", w, k, j))
+                        .join(" - ")
+                )?;
+                vars[VBIN].insert(p.clone());
+                // the discount never exceeds the cost of the recombination it applies to
+                res.push((p, (1.0 - phase_cost) * cost(&u, *h)));
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+fn write_con_forbidden_recombination<W: io::Write>(
+    g: &DiGraphMap<ff::Node, ff::EdgeType>,
+    color_conservation_vars: &FxHashMap<String, (ff::Node, ff::Node)>,
+    forbidden: &FxHashSet<u64>,
+    mismatches: &Mismatches,
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
+    writeln!(out, "\\\n\\ forbidden recombination constraints \n\\")?;
+
+    let mut edge2t: FxHashMap<(ff::Node, ff::Node), Vec<&String>> = FxHashMap::default();
+    color_conservation_vars
+        .iter()
+        .filter(|(_, (u, _))| forbidden.contains(&u.node))
+        .for_each(|(t, e)| edge2t.entry(*e).or_default().push(t));
+
+    // any "dashed" edge of a forbidden node that is used in the matching must conserve the color,
+    // unless the color is interrupted by a mismatch anyway
+    g.all_edges()
+        .filter(|(u, v, t)| {
+            **t == ff::EdgeType::Dashed
+                && forbidden.contains(&u.node)
+                && !mismatches.exempt.contains(&(*u, *v))
+        })
+        .try_for_each(|(u, v, _)| match edge2t.get(&(u, v)) {
+            None => writeln!(out, "x_{}_{} = 0", u, v),
+            Some(ts) => writeln!(out, "{} - x_{}_{} >= 0", ts.iter().join(" + "), u, v),
+        })
+}
+
+fn write_con_minmax<W: io::Write>(
+    g: &DiGraphMap<ff::Node, ff::EdgeType>,
+    sources: &FxHashSet<ff::Node>,
+    color_conservation_vars: &FxHashMap<String, (ff::Node, ff::Node)>,
+    costs: &Option<FxHashMap<ff::Node, f64>>,
+    mismatches: &Mismatches,
+    vars: &mut [FxHashSet<String>],
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
+    writeln!(out, "\\\n\\ founder labeling constraints \n\\")?;
+
+    // each founder is identified by its source node; fix order to obtain reproducible programs
+    let mut founders: Vec<&ff::Node> = sources.iter().collect();
+    founders.sort();
+
+    // label z_v_k indicates that node v is traversed by the k-th founder; in joint mode, sources
+    // may remain unused, in which case the k-th founder does not exist
+    for (k, s) in founders.iter().enumerate() {
+        writeln!(out, "z_{}_{} - y_{} = 0", s, k, s)?;
+    }
+    for v in g.nodes() {
+        writeln!(
+            out,
+            "{} - y_{} = 0",
+            (0..founders.len())
+                .map(|k| format!("z_{}_{}", v, k))
+                .join(" + "),
+            v
+        )?;
+        (0..founders.len()).for_each(|k| {
+            vars[VBIN].insert(format!("z_{}_{}", v, k));
+        });
+    }
+    // labels are passed on along "solid" and matched "dashed" edges
+    for (u, v, t) in g.all_edges() {
+        for k in 0..founders.len() {
+            match t {
+                ff::EdgeType::Solid => writeln!(out, "z_{}_{} - z_{}_{} = 0", u, k, v, k)?,
+                ff::EdgeType::Dashed => {
+                    writeln!(out, "z_{}_{} - z_{}_{} + x_{}_{} <= 1", u, k, v, k, u, v)?;
+                    writeln!(out, "z_{}_{} - z_{}_{} + x_{}_{} <= 1", v, k, u, k, u, v)?;
+                }
+            }
         }
     }
 
+    writeln!(out, "\\\n\\ per-founder recombination constraints \n\\")?;
+
+    // s_u indicates a recombination at incoming node u, i.e., u is traversed, but none of the
+    // color conservation variables of its "dashed" edges is set, nor is the traversal exempt due
+    // to a mismatch
+    let mut node2t: FxHashMap<ff::Node, Vec<String>> = FxHashMap::default();
+    color_conservation_vars
+        .iter()
+        .filter(|(_, e)| !mismatches.exempt.contains(e))
+        .for_each(|(t, (u, _))| node2t.entry(*u).or_default().push(t.clone()));
+    mismatches
+        .exempt
+        .iter()
+        .for_each(|(u, w)| node2t.entry(*u).or_default().push(format!("x_{}_{}", u, w)));
+
+    let mut bounds: Vec<Vec<String>> = vec![Vec::new(); founders.len()];
+    for u in g.nodes().filter(|u| u.direction == ff::Direction::In) {
+        let s = format!("s_{}", u);
+        match node2t.get(&u) {
+            None => writeln!(out, "{} - y_{} >= 0", s, u)?,
+            Some(ts) => writeln!(out, "{} - y_{} + {} >= 0", s, u, ts.iter().join(" + "))?,
+        }
+        vars[VBIN].insert(s.clone());
+        for (k, b) in bounds.iter_mut().enumerate() {
+            let q = format!("q_{}_{}", u, k);
+            writeln!(out, "{} - {} - z_{}_{} >= -1", q, s, u, k)?;
+            b.push(match costs {
+                None => q.clone(),
+                Some(c) => format!("{} {}", c.get(&u).unwrap_or(&1.0), q),
+            });
+            vars[VBIN].insert(q);
+        }
+    }
+
+    // R bounds the number of recombinations of each founder
+    for b in bounds.iter().filter(|b| !b.is_empty()) {
+        writeln!(out, "R - {} >= 0", b.join(" - "))?;
+    }
+    if costs.is_none() {
+        vars[VINT].insert("R".to_string());
+    }
+
     Ok(())
 }
 
@@ -363,20 +788,11 @@ fn write_con_flow_matching<W: io::Write>(
 fn write_con_flow_adj_matching<W: io::Write>(
     g: &DiGraphMap<ff::Node, ff::EdgeType>,
     flowmap: &FxHashMap<(Handle, Handle), usize>,
+    joint: bool,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     writeln!(out, "\\\n\\ flow adjacency matching constraints \n\\")?;
 
-    // intermediary data structure that reports the total flow for adjacency, no matter the flow
-    // orientation
-    let mut total_flow: FxHashMap<(Handle, Handle), usize> = FxHashMap::default();
-    flowmap.into_iter().for_each(|(e, c)| {
-        total_flow
-            .entry(normalize(e))
-            .and_modify(|x| *x += c)
-            .or_insert(*c);
-    });
-
     // intermediary data structure to access adjacencies in a convenient way
     let mut adjs: FxHashMap<(Handle, Handle), Vec<(ff::Node, ff::Node)>> = FxHashMap::default();
     g.all_edges()
@@ -390,6 +806,28 @@ fn write_con_flow_adj_matching<W: io::Write>(
             .push((u, v));
         });
 
+    if joint {
+        // founders are free to choose their adjacencies, as long as every adjacency of the
+        // variation graph is covered at least once
+        return adjs.values().try_for_each(|es| {
+            writeln!(
+                out,
+                "{} >= 1",
+                es.iter().map(|(u, v)| format!("x_{}_{}", u, v)).join(" + ")
+            )
+        });
+    }
+
+    // intermediary data structure that reports the total flow for adjacency, no matter the flow
+    // orientation
+    let mut total_flow: FxHashMap<(Handle, Handle), usize> = FxHashMap::default();
+    flowmap.into_iter().for_each(|(e, c)| {
+        total_flow
+            .entry(normalize(e))
+            .and_modify(|x| *x += c)
+            .or_insert(*c);
+    });
+
     for (e, f) in total_flow.iter() {
         writeln!(
             out,
@@ -431,16 +869,60 @@ fn write_vars<W: io::Write>(
     Ok(())
 }
 
+fn find_mismatches(
+    g: &DiGraphMap<ff::Node, ff::EdgeType>,
+    haplotypes: &Vec<(String, Vec<Handle>)>,
+    penalty: usize,
+) -> Mismatches {
+    let mut hap_adjs: FxHashSet<(Handle, Handle)> = FxHashSet::default();
+    for (_, s) in haplotypes.iter() {
+        s.iter().tuple_windows().for_each(|(u, v)| {
+            hap_adjs.insert((*u, *v));
+            hap_adjs.insert((v.flip(), u.flip()));
+        });
+    }
+
+    let adjs: Vec<(ff::Node, ff::Node)> = g
+        .all_edges()
+        .filter(|(u, v, t)| {
+            **t == ff::EdgeType::Solid
+                && !hap_adjs.contains(&(
+                    Handle::pack(u.node, u.etype == ff::ExtremityType::Tail),
+                    Handle::pack(v.node, v.etype == ff::ExtremityType::Head),
+                ))
+        })
+        .map(|(u, v, _)| (u, v))
+        .sorted()
+        .collect();
+
+    let nodes: FxHashSet<ff::Node> = adjs.iter().flat_map(|(u, v)| [*u, *v]).collect();
+    let exempt = g
+        .all_edges()
+        .filter(|(u, w, t)| **t == ff::EdgeType::Dashed && (nodes.contains(u) || nodes.contains(w)))
+        .map(|(u, w, _)| (u, w))
+        .collect();
+
+    Mismatches {
+        penalty,
+        adjs,
+        exempt,
+    }
+}
+
 fn write_lp<W: io::Write>(
     g: &DiGraphMap<ff::Node, ff::EdgeType>,
     haplotypes: &Vec<(String, Vec<Handle>)>,
     flowmap: &FxHashMap<(Handle, Handle), usize>,
+    opts: &ModelOptions,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
+    let costs = &opts.costs.as_ref().map(|c| c.of_in_nodes(g));
     let mut vars: [FxHashSet<String>; VNIL] = [FxHashSet::default(), FxHashSet::default()];
     let mut tmp = io::BufWriter::new(Vec::new());
     let mut color_vars: FxHashMap<ff::Node, FxHashSet<(usize, usize)>> = FxHashMap::default();
-    let mut color_conservation_vars: FxHashSet<String> = FxHashSet::default();
+    // maps each color conservation variable to the "dashed" edge at which a recombination would
+    // occur
+    let mut color_conservation_vars: FxHashMap<String, (ff::Node, ff::Node)> = FxHashMap::default();
     let mut node_multimap = map_node_multiplicity(flowmap);
 
     let (src, snk) = ff::term_nodes(&g);
@@ -476,17 +958,34 @@ fn write_lp<W: io::Write>(
             .join(", ")
     );
 
+    let mismatches = match opts.mismatch {
+        None => Mismatches::default(),
+        Some(p) => {
+            let m = find_mismatches(g, haplotypes, p);
+            log::info!(
+                "{} founder adjacencies are not traversed by any haplotype and charged with mismatch penalty {}",
+                m.adjs.len(),
+                p
+            );
+            m
+        }
+    };
+
     let totflow: usize = node_multimap.values().sum::<usize>();
     for (i, (_, hap)) in haplotypes.iter().enumerate() {
+        // haplotypes (or fragments thereof) do not necessarily start at a source, so flow nodes
+        // are picked up at their first node; likewise, haplotype adjacencies missing from the
+        // founders interrupt the coloring, which, if mismatches are allowed, resumes at the next
+        // node traversed by founders
         write_con_color_contiguity(
             g,
-            &src,
+            &FxHashSet::default(),
             hap,
             i + 1,
             &mut color_vars,
             &mut color_conservation_vars,
             &mut vars,
-            false,
+            true,
             &mut tmp,
         )?;
 
@@ -504,27 +1003,158 @@ fn write_lp<W: io::Write>(
             &mut tmp,
         )?;
     }
-    vars[VINT].insert("T".to_string());
+    // with recombination costs, T is the total cost of recombining at every node traversal, which
+    // is not necessarily integral
+    let cost = |v: &ff::Node| match costs {
+        None => 1.0,
+        Some(c) => *c.get(v).unwrap_or(&1.0),
+    };
+    let node_cost = |v: u64| match &opts.costs {
+        None => 1.0,
+        Some(c) => *c.nodes.get(&v).unwrap_or(&1.0),
+    };
+    // adjacency costs depend on which founder adjacencies enter a node, so T is determined by the
+    // incoming nodes in use, as in joint mode
+    let variable_t = opts.joint.is_some()
+        || opts
+            .costs
+            .as_ref()
+            .map(|c| !c.adjs.is_empty())
+            .unwrap_or(false);
+    if costs.is_none() {
+        vars[VINT].insert("T".to_string());
+    }
+    // in joint mode, the number of node traversals is variable and only bounded by the number of
+    // incoming nodes of the graph
+    let in_nodes: Vec<ff::Node> = g
+        .nodes()
+        .filter(|v| v.direction == ff::Direction::In)
+        .collect();
+    // cost of a recombination at given node that leaves given haplotype
+    let hap_cost = |v: &ff::Node, h: usize| match &opts.weights {
+        None => cost(v),
+        Some(ws) => cost(v) * ws[h - 1],
+    };
+    let phase_switch_vars = match &opts.phase_switch {
+        None => Vec::new(),
+        Some((phase_cost, samples)) => write_con_phase_switch(
+            g,
+            &color_vars,
+            (*phase_cost, samples),
+            &hap_cost,
+            &mismatches,
+            &mut vars,
+            &mut tmp,
+        )?,
+    };
+
+    let recombination_vars = match &opts.weights {
+        None => None,
+        Some(_) => Some(write_con_weighted_recombination(
+            g,
+            &color_vars,
+            &color_conservation_vars,
+            &hap_cost,
+            &mismatches,
+            &mut vars,
+            &mut tmp,
+        )?),
+    };
+
+    let in_cost = in_nodes.iter().map(cost).sum::<f64>();
+    let (maxflow, maxcost) = match opts.joint {
+        None if variable_t => (totflow, in_cost),
+        None => (
+            totflow,
+            node_multimap
+                .iter()
+                .map(|(v, k)| node_cost(v.unpack_number()) * *k as f64)
+                .sum::<f64>(),
+        ),
+        Some(_) => (in_nodes.len(), in_cost),
+    };
 
     // write objective function
     writeln!(out, "Minimize")?;
-    write_obj(&color_conservation_vars, out)?;
+    if opts.minmax {
+        // the total number of recombinations can never exceed T, nor can the total penalty of
+        // mismatches exceed that of all of them, so weighting R by their sum + 1 ensures that the
+        // maximum is minimized first and the total only second
+        write!(
+            out,
+            "{} R + ",
+            maxcost + (mismatches.penalty * mismatches.adjs.len()) as f64 + 1.0
+        )?;
+    }
+    write_obj(
+        &color_conservation_vars,
+        costs,
+        recombination_vars.as_deref(),
+        &mismatches,
+        &phase_switch_vars,
+        out,
+    )?;
     writeln!(out, "Subject To")?;
-    writeln!(out, "T = {}", totflow)?;
+    if variable_t {
+        writeln!(
+            out,
+            "T - {} = 0",
+            in_nodes
+                .iter()
+                .map(|v| match costs {
+                    None => format!("y_{}", v),
+                    Some(_) => format!("{} y_{}", cost(v), v),
+                })
+                .join(" - ")
+        )?;
+    } else {
+        writeln!(out, "T = {}", maxcost)?;
+    }
+    if let Some(nf) = opts.joint {
+        // the number of founders remains that of the given founder set
+        writeln!(
+            out,
+            "{} = {}",
+            src.iter().map(|v| format!("y_{}", v)).join(" + "),
+            nf
+        )?;
+    }
     // write constraints
     write_con_match(g, &mut vars, out)?;
-    write_con_flow_matching(g, &node_multimap, out)?;
-    write_con_flow_adj_matching(g, flowmap, out)?;
+    if opts.joint.is_none() {
+        write_con_flow_matching(g, &node_multimap, out)?;
+    }
+    write_con_flow_adj_matching(g, flowmap, opts.joint.is_some(), out)?;
     write_solid_edges(g, &mut vars, out)?; // fixed, used in min2seq
     write_con_flow_solid(g, &mut vars, out)?;
-    write_con_flow_dashed(g, totflow, &mut vars, out)?;
+    write_con_flow_dashed(g, maxflow, &mut vars, out)?;
     write_con_flow_source(&src, &mut vars, out)?;
 
-    out.write(&tmp.into_inner()?)?;
+    out.write_all(&tmp.into_inner()?)?;
     write_con_color_singularity(&color_vars, out)?;
+    if !opts.forbidden.is_empty() {
+        write_con_forbidden_recombination(
+            g,
+            &color_conservation_vars,
+            &opts.forbidden,
+            &mismatches,
+            out,
+        )?;
+    }
+    if opts.minmax {
+        write_con_minmax(
+            g,
+            &src,
+            &color_conservation_vars,
+            costs,
+            &mismatches,
+            &mut vars,
+            out,
+        )?;
+    }
 
     // write bounds and collected variables
-    write_bounds(g, totflow, out)?;
+    write_bounds(g, maxflow, out)?;
     write_vars(&vars, out)?;
     writeln!(out, "End")?;
     Ok(())
@@ -726,25 +1356,76 @@ fn construct_graph_from_adj_multiplicities(
         }
     }
 
-    //    log::debug!("graph: \n{}", Dot::new(&graph));
     graph
 }
 
+// flow graph with nodes identified as in the variables of the linear program, so that matchings
+// and colors of a solution can be highlighted
+fn dump_graph(
+    graph: &DiGraphMap<ff::Node, ff::EdgeType>,
+    names: &ff::SegmentNames,
+    solution: Option<&[(String, f64)]>,
+) -> ff::GraphDump {
+    let (matchings, colors) = solution.map(ff::min_solution_choices).unwrap_or_default();
+
+    let mut nodes: Vec<ff::Node> = graph.nodes().collect();
+    nodes.sort_unstable();
+    let mut edges: Vec<(ff::Node, ff::Node, &ff::EdgeType)> = graph.all_edges().collect();
+    edges.sort_unstable_by_key(|(u, v, _)| (*u, *v));
+
+    ff::GraphDump {
+        directed: true,
+        nodes: nodes
+            .into_iter()
+            .map(|v| ff::DumpNode {
+                id: v.to_string(),
+                label: format!(
+                    "{}{}{} #{}",
+                    v.direction,
+                    names.name(v.node as usize),
+                    v.etype,
+                    v.id
+                ),
+                color: colors.get(&v.to_string()).copied(),
+            })
+            .collect(),
+        edges: edges
+            .into_iter()
+            .map(|(u, v, t)| {
+                let (u, v) = (u.to_string(), v.to_string());
+                ff::DumpEdge {
+                    label: String::new(),
+                    style: *t,
+                    highlight: matchings.contains(&(u.clone(), v.clone())),
+                    source: u,
+                    target: v,
+                }
+            })
+            .collect(),
+    }
+}
+
+type AdjMultiplicity = FxHashMap<(Handle, Handle), usize>;
+
+// multiplicity of each founder adjacency, along with the number of founders
 fn read_founderseq_adj_multiplicity<R: io::Read>(
     data: io::BufReader<R>,
-) -> Result<FxHashMap<(Handle, Handle), usize>, String> {
-    let mut multiplicity: FxHashMap<(Handle, Handle), usize> = FxHashMap::default();
+    names: &ff::SegmentNames,
+) -> Result<(AdjMultiplicity, usize), String> {
+    let mut multiplicity: AdjMultiplicity = FxHashMap::default();
+    let mut n = 0;
 
     for line_op in data.lines() {
         if let Ok(line) = line_op {
-            let walk = ff::parse_walk(&line)?;
+            let walk = ff::parse_walk(&line, names)?;
+            n += 1;
             // insert first element of walk
             walk.into_iter().tuple_windows().for_each(|a| {
                 multiplicity.entry(a).and_modify(|x| *x += 1).or_insert(1);
             });
         }
     }
-    Ok(multiplicity)
+    Ok((multiplicity, n))
 }
 
 fn map_node_multiplicity(fmap: &FxHashMap<(Handle, Handle), usize>) -> FxHashMap<Handle, usize> {
@@ -757,14 +1438,20 @@ fn map_node_multiplicity(fmap: &FxHashMap<(Handle, Handle), usize>) -> FxHashMap
 
 fn map_haplotype_adj_multiplicity(
     haps: &Vec<(String, Vec<Handle>)>,
+    weights: Option<&[f64]>,
 ) -> FxHashMap<(Handle, Handle), usize> {
-    let mut res: FxHashMap<(Handle, Handle), usize> = FxHashMap::default();
-    for (_, s) in haps.into_iter() {
+    // weighted haplotypes contribute their (possibly fractional) weight, which is rounded up in
+    // the total
+    let mut res: FxHashMap<(Handle, Handle), f64> = FxHashMap::default();
+    for (k, (_, s)) in haps.into_iter().enumerate() {
+        let w = weights.map(|ws| ws[k]).unwrap_or(1.0);
         s.into_iter().tuple_windows().for_each(|(u, v)| {
-            res.entry((*u, *v)).and_modify(|x| *x += 1).or_insert(1);
+            res.entry((*u, *v)).and_modify(|x| *x += w).or_insert(w);
         })
     }
-    res
+    res.into_iter()
+        .map(|(e, x)| (e, x.ceil() as usize))
+        .collect()
 }
 
 fn normalize(e: &(Handle, Handle)) -> (Handle, Handle) {
@@ -780,7 +1467,9 @@ fn normalize(e: &(Handle, Handle)) -> (Handle, Handle) {
 fn merge_adj_multiplicities(
     flowmap: &FxHashMap<(Handle, Handle), usize>,
     hapmap: &FxHashMap<(Handle, Handle), usize>,
-) -> FxHashMap<(Handle, Handle), usize> {
+    joint: bool,
+    mismatch: bool,
+) -> Result<FxHashMap<(Handle, Handle), usize>, String> {
     log::info!("merging adjacency multiplicities");
 
     let mut total_flow: FxHashMap<(Handle, Handle), usize> = FxHashMap::default();
@@ -800,41 +1489,117 @@ fn merge_adj_multiplicities(
     );
 
     let mut merged: FxHashMap<(Handle, Handle), usize> = FxHashMap::default();
-    FxHashSet::from_iter(flowmap.keys().chain(hapmap.keys()))
-        .into_iter()
-        .for_each(|&e| {
-            merged.insert(
-                e,
-                std::cmp::max(
-                    *flowmap.get(&e).or(Some(&0)).unwrap(),
-                    min(
-                        *total_flow.get(&normalize(&e)).expect(&format!(
-                            "edge {}{} not contained in total flow map",
-                            ff::v2str(&e.0),
-                            ff::v2str(&e.1)
-                        )),
-                        *hapmap.get(&e).or(Some(&0)).unwrap(),
+    if joint {
+        // founder adjacencies are not fixed, so the multiplicity of an adjacency is only bounded by
+        // its occurrences in either set
+        FxHashSet::from_iter(flowmap.keys().chain(hapmap.keys()))
+            .into_iter()
+            .for_each(|&e| {
+                merged.insert(
+                    e,
+                    std::cmp::max(
+                        *flowmap.get(&e).unwrap_or(&0),
+                        *hapmap.get(&e).unwrap_or(&0),
                     ),
-                ),
-            );
-        });
-    merged
+                );
+            });
+        return Ok(merged);
+    }
+    for &e in FxHashSet::from_iter(flowmap.keys().chain(hapmap.keys())) {
+        // haplotype adjacencies missing from the founders are tolerated only as mismatches
+        let f = match (total_flow.get(&normalize(&e)), mismatch) {
+            (Some(f), _) => *f,
+            (None, true) => 0,
+            (None, false) => {
+                return Err(format!(
+                    "haplotype adjacency {}{} is not traversed by any founder, allow it by setting a mismatch penalty",
+                    ff::v2str(&e.0),
+                    ff::v2str(&e.1)
+                ))
+            }
+        };
+        merged.insert(
+            e,
+            std::cmp::max(
+                *flowmap.get(&e).or(Some(&0)).unwrap(),
+                min(f, *hapmap.get(&e).or(Some(&0)).unwrap()),
+            ),
+        );
+    }
+    Ok(merged)
 }
 
-fn read_haplotypes<R: io::Read>(
-    data: io::BufReader<R>,
-) -> Result<Vec<(String, Vec<Handle>)>, String> {
-    let mut res: Vec<(String, Vec<Handle>)> = Vec::new();
+// a phase switch costs at least nothing and at most as much as a recombination
+fn parse_relative_cost(c: &str) -> Result<f64, String> {
+    match f64::from_str(c) {
+        Ok(x) if (0.0..=1.0).contains(&x) => Ok(x),
+        _ => Err(format!("relative cost \"{}\" is not between 0 and 1", c)),
+    }
+}
 
+// recombination costs must be finite and non-negative
+fn parse_cost(c: &str, what: &str) -> Result<f64, String> {
+    match f64::from_str(c) {
+        Ok(x) if x.is_finite() && x >= 0.0 => Ok(x),
+        _ => Err(format!("invalid cost \"{}\" of {}", c, what)),
+    }
+}
+
+// reads costs of nodes (given by their id) and adjacencies (given as two-step walk), overriding
+// those already present
+fn read_recombination_costs<R: io::Read>(
+    data: io::BufReader<R>,
+    names: &ff::SegmentNames,
+    costs: &mut RecombinationCosts,
+) -> Result<(), String> {
     for line_op in data.lines() {
         if let Ok(line) = line_op {
-            res.push((
-                line[..line.find('\t').unwrap()].to_string(),
-                ff::parse_walk(&line)?,
-            ));
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut it = line.split('\t');
+            let v = it.next().unwrap();
+            if v.starts_with('>') || v.starts_with('<') {
+                let c = it
+                    .next()
+                    .ok_or(format!("missing cost for adjacency {}", v))?;
+                match ff::parse_walk(&format!("\t{}", v), names)?[..] {
+                    [a, b] => costs.insert_adj(a, b, parse_cost(c, &format!("adjacency {}", v))?),
+                    _ => return Err(format!("adjacency {} must consist of two steps", v)),
+                }
+            } else {
+                let c = it.next().ok_or(format!("missing cost for node {}", v))?;
+                costs
+                    .nodes
+                    .insert(names.id(v)? as u64, parse_cost(c, &format!("node {}", v))?);
+            }
         }
     }
+    Ok(())
+}
 
+// costs given by tag <tag>:i:<cost> or <tag>:f:<cost> of segments and links
+fn tag_recombination_costs(gfa: &ff::StreamedGFA, tag: &str) -> Result<RecombinationCosts, String> {
+    let (int, float) = (format!("{}:i:", tag), format!("{}:f:", tag));
+    let value = |tags: &String| -> Option<String> {
+        tags.split('\t')
+            .find_map(|t| t.strip_prefix(&int).or_else(|| t.strip_prefix(&float)))
+            .map(|x| x.to_string())
+    };
+
+    let mut res = RecombinationCosts::default();
+    for (v, tags) in gfa.annotations.segment_tags.iter() {
+        if let Some(c) = value(tags) {
+            let what = format!("segment {}", gfa.names.name(*v));
+            res.nodes.insert(*v as u64, parse_cost(&c, &what)?);
+        }
+    }
+    for (Edge(u, v), tags) in gfa.annotations.link_tags.iter() {
+        if let Some(c) = value(tags) {
+            let what = format!("link {}", gfa.names.v2seq(&[*u, *v], ""));
+            res.insert_adj(*u, *v, parse_cost(&c, &what)?);
+        }
+    }
     Ok(res)
 }
 
@@ -843,12 +1608,38 @@ fn main() -> Result<(), std::io::Error> {
     // initialize command line parser & parse command line arguments
     let params = Command::parse();
 
+    let mut tag_costs = None;
+    let names = match &params.graph {
+        None => ff::SegmentNames::default(),
+        Some(g) if params.cost_tag.is_some() => {
+            log::info!("loading graph {}", g);
+            let gfa = ff::read_gfa_file(
+                g,
+                &ff::GFAReadOptions {
+                    tags: true,
+                    ..Default::default()
+                },
+            )?;
+            let tag = params.cost_tag.as_ref().unwrap();
+            log::info!("reading recombination costs from tag {}", tag);
+            tag_costs = Some(
+                tag_recombination_costs(&gfa, tag)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?,
+            );
+            gfa.names
+        }
+        Some(g) => {
+            log::info!("reading segment names from graph {}", g);
+            ff::read_segment_names(g)?
+        }
+    };
+
     log::info!(
         "reading adjacency multiplicities from founder set {}",
         params.founder_set
     );
-    let founder_data = io::BufReader::new(fs::File::open(&params.founder_set)?);
-    let flow_multi = read_founderseq_adj_multiplicity(founder_data).unwrap();
+    let founder_data = ff::open_file(&params.founder_set)?;
+    let (flow_multi, nfounders) = read_founderseq_adj_multiplicity(founder_data, &names).unwrap();
     log::debug!(
         "flow multiplicity map: {}",
         flow_multi
@@ -864,9 +1655,36 @@ fn main() -> Result<(), std::io::Error> {
         "reading adjacency multiplicities from haplotype set {}",
         params.haplotype_set
     );
-    let hap_data = io::BufReader::new(fs::File::open(&params.haplotype_set)?);
-    let haplotypes = read_haplotypes(hap_data).unwrap();
-    let hap_multimap = map_haplotype_adj_multiplicity(&haplotypes);
+    let hap_data = ff::open_file(&params.haplotype_set)?;
+    let (haplotypes, weights): (Vec<(String, Vec<Handle>)>, Vec<f64>) =
+        ff::read_weighted_haplotype_fragments(hap_data, &names)
+            .unwrap()
+            .into_iter()
+            .map(|(name, walk, w)| ((name, walk), w))
+            .unzip();
+    let phase_switch = params.phase_switch_cost.map(|c| {
+        let samples: Vec<String> = haplotypes
+            .iter()
+            .map(|(name, _)| ff::parse_pansn(name).sample)
+            .collect();
+        log::info!(
+            "grouping haplotypes into {} samples, phase switches cost {}",
+            samples.iter().unique().count(),
+            c
+        );
+        (c, samples)
+    });
+
+    let weights = if params.weighted {
+        log::info!(
+            "weighting haplotypes, total weight is {}",
+            weights.iter().sum::<f64>()
+        );
+        Some(weights)
+    } else {
+        None
+    };
+    let hap_multimap = map_haplotype_adj_multiplicity(&haplotypes, weights.as_deref());
     log::debug!(
         "haplotype multiplicity map: {}",
         hap_multimap
@@ -877,7 +1695,13 @@ fn main() -> Result<(), std::io::Error> {
     );
 
     log::info!("merging multiplicities between the two sets");
-    let merged_multimap = merge_adj_multiplicities(&flow_multi, &hap_multimap);
+    let merged_multimap = merge_adj_multiplicities(
+        &flow_multi,
+        &hap_multimap,
+        params.joint,
+        params.mismatch_penalty.is_some(),
+    )
+    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
     log::debug!(
         "merged multiplicity map: {}",
         merged_multimap
@@ -889,6 +1713,13 @@ fn main() -> Result<(), std::io::Error> {
 
     log::info!("constructing graph from multiplicity map");
     let g = construct_graph_from_adj_multiplicities(&merged_multimap);
+    if let Some(prefix) = &params.dump_graph {
+        let solution = match &params.solution {
+            None => None,
+            Some(f) => Some(ff::read_flow_values(f)?),
+        };
+        dump_graph(&g, &names, solution.as_deref()).write_files(prefix)?;
+    }
 
     //    log::info!(
     //        "colorizing graph based on haplotype sequences of {}",
@@ -909,9 +1740,56 @@ fn main() -> Result<(), std::io::Error> {
         merged_multimap.values().sum::<usize>()
     );
 
-    let mut out = io::BufWriter::new(std::io::stdout());
-    write_lp(&g, &haplotypes, &flow_multi, &mut out)?;
-    out.flush()?;
+    let costs = match (tag_costs, &params.recomb_costs) {
+        (None, None) => None,
+        (costs, file) => {
+            let mut costs = costs.unwrap_or_default();
+            if let Some(f) = file {
+                log::info!("reading recombination costs from {}", f);
+                let cost_data = ff::open_file(f)?;
+                read_recombination_costs(cost_data, &names, &mut costs)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            }
+            log::info!(
+                "recombination costs are given for {} nodes and {} adjacencies",
+                costs.nodes.len(),
+                costs.adjs.len() / 2
+            );
+            Some(costs)
+        }
+    };
+
+    let forbidden = match params.forbidden {
+        None => FxHashSet::default(),
+        Some(f) => {
+            log::info!("reading forbidden recombination regions from {}", f);
+            let forbidden_data = ff::open_file(&f)?;
+            ff::read_node_ranges(forbidden_data, &names).unwrap()
+        }
+    };
+    log::info!("recombinations are forbidden at {} nodes", forbidden.len());
+
+    let opts = ModelOptions {
+        costs,
+        forbidden,
+        minmax: params.minmax,
+        joint: if params.joint {
+            log::info!("optimizing adjacencies of {} founders jointly", nfounders);
+            Some(nfounders)
+        } else {
+            None
+        },
+        mismatch: params.mismatch_penalty,
+        weights: weights.map(|ws| {
+            let m = ws.iter().cloned().fold(0.0, f64::max);
+            ws.iter().map(|w| w / m).collect()
+        }),
+        phase_switch,
+    };
+
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+    write_lp(&g, &haplotypes, &flow_multi, &opts, &mut out)?;
+    ff::finish(out)?;
 
     log::info!("done");
     Ok(())
@@ -0,0 +1,160 @@
+/* standard use */
+use std::io::{self, Write};
+
+/* crate use */
+use clap::Parser;
+use handlegraph::pathhandlegraph::GraphPathNames;
+
+/* private use */
+use founderset as ff;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    version = "0.1",
+    author = "Daniel Doerr <daniel.doerr@hhu.de>",
+    about = "Write overlapping windows along a reference path as regions for subgr"
+)]
+pub struct Command {
+    #[clap(index = 1, help = "graph in GFA1 format", required = true)]
+    pub graph: String,
+
+    #[clap(
+        short = 'r',
+        long = "reference",
+        help = "Name of the reference path along which windows are placed",
+        required = true
+    )]
+    pub reference: String,
+
+    #[clap(
+        short = 's',
+        long = "size",
+        help = "Window size in bp",
+        default_value = "1000000"
+    )]
+    pub size: usize,
+
+    #[clap(
+        short = 'v',
+        long = "overlap",
+        help = "Overlap of consecutive windows in bp",
+        default_value = "100000"
+    )]
+    pub overlap: usize,
+
+    #[clap(
+        short = 'c',
+        long = "cut-nodes",
+        help = "Extend windows to the nearest cut nodes of the graph on the reference path, so that windows are independent regions spanned by all haplotypes"
+    )]
+    pub cut_nodes: bool,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+fn main() -> Result<(), io::Error> {
+    env_logger::init();
+
+    // initialize command line parser & parse command line arguments
+    let params = Command::parse();
+    if params.overlap >= params.size {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "window overlap must be smaller than window size",
+        ));
+    }
+
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+
+    log::info!("loading graph {}", &params.graph);
+    let select = |name: &str| name == params.reference;
+    let ff::StreamedGFA {
+        graph,
+        lengths,
+        annotations,
+        ..
+    } = ff::read_gfa_file(
+        &params.graph,
+        &ff::GFAReadOptions {
+            lengths: true,
+            paths: Some(&select),
+            ..Default::default()
+        },
+    )?;
+    let path_id = graph
+        .get_path_id(params.reference.as_bytes())
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("reference path {} not found", params.reference),
+            )
+        })?;
+    let walk = &graph.get_path(&path_id).unwrap().nodes;
+    let offsets = ff::step_offsets(walk, &lengths, &annotations.overlaps);
+    let length = *offsets.last().unwrap();
+    log::info!("reference path {} has length {}", params.reference, length);
+
+    // steps of the reference path at which windows may start and end; cut nodes separate the
+    // walk into independent regions, whose boundaries are shared by all walks through the graph
+    let boundaries: Option<Vec<usize>> = if params.cut_nodes {
+        log::info!("identifying cut nodes of graph");
+        let cut_nodes = ff::articulation_points(&graph);
+        let mut res = vec![0];
+        for region in ff::split_at_cut_nodes(walk, &cut_nodes).iter() {
+            res.push(res.last().unwrap() + region.len() - 1);
+        }
+        log::info!(
+            "reference path is divided into {} independent regions",
+            res.len() - 1
+        );
+        Some(res)
+    } else {
+        None
+    };
+
+    // regions are 1-based and inclusive, as expected by subgr
+    let step = params.size - params.overlap;
+    let mut start = 0;
+    let mut last_end = 0;
+    let mut n = 0;
+    while start < length {
+        let end = (start + params.size).min(length);
+        let (s, e) = match &boundaries {
+            Some(boundaries) => (
+                boundaries
+                    .iter()
+                    .map(|&k| offsets[k])
+                    .filter(|&x| x <= start)
+                    .max()
+                    .unwrap_or(0),
+                boundaries
+                    .iter()
+                    .map(|&k| offsets[k + 1])
+                    .filter(|&x| x >= end)
+                    .min()
+                    .unwrap_or(length),
+            ),
+            None => (start, end),
+        };
+        // extended windows may be contained in the previous one
+        if e > last_end {
+            writeln!(out, "{}:{}-{}", params.reference, s + 1, e)?;
+            n += 1;
+            last_end = e;
+        }
+        if end == length {
+            break;
+        }
+        start += step;
+    }
+    ff::finish(out)?;
+    log::info!("wrote {} windows", n);
+
+    log::info!("done");
+    Ok(())
+}
@@ -0,0 +1,319 @@
+/* standard use */
+use std::io::{self, BufRead, Write};
+
+/* crate use */
+use clap::Parser;
+use handlegraph::handle::Handle;
+use rustc_hash::FxHashMap;
+
+/* private use */
+use founderset as ff;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    version = "0.1",
+    author = "Daniel Doerr <daniel.doerr@hhu.de>",
+    about = "Draw founder mosaics as tracks colored by haplotype in SVG format"
+)]
+pub struct Command {
+    #[clap(
+        index = 1,
+        help = "Founder mosaics in long format of min2seq (or stitch)",
+        required = true
+    )]
+    pub mosaics: String,
+
+    #[clap(
+        short = 'g',
+        long = "graph",
+        help = "Graph (GFA1 format) whose segment names are used in founder mosaics, required if these are not numeric"
+    )]
+    pub graph: Option<String>,
+
+    #[clap(
+        short = 'x',
+        long = "node-width",
+        help = "Width of a node in pixels",
+        default_value = "8"
+    )]
+    pub node_width: f64,
+
+    #[clap(
+        short = 'H',
+        long = "html",
+        help = "Embed drawing in a self-contained HTML page"
+    )]
+    pub html: bool,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+const TRACK_HEIGHT: f64 = 14.0;
+const TRACK_GAP: f64 = 8.0;
+const AXIS_HEIGHT: f64 = 30.0;
+const MARGIN: f64 = 10.0;
+const CHAR_WIDTH: f64 = 7.0;
+const LEGEND_WIDTH: f64 = 160.0;
+
+// part of a founder that follows a single haplotype; consecutive segments share the recombination
+// node at their boundary
+struct Segment {
+    haplotype: String,
+    walk: Vec<Handle>,
+}
+
+// reads founders in long format, i.e., lines of founder, haplotype name, and walk
+fn read_mosaics(
+    file: &str,
+    names: &ff::SegmentNames,
+) -> Result<Vec<(String, Vec<Segment>)>, io::Error> {
+    let mut res: Vec<(String, Vec<Segment>)> = Vec::new();
+    let mut index: FxHashMap<String, usize> = FxHashMap::default();
+
+    for line_op in ff::open_file(file)?.lines() {
+        let line = line_op?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 || line.starts_with('#') {
+            continue;
+        }
+        let walk = ff::parse_walk(&fields[1..].join("\t"), names)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        if walk.is_empty() {
+            continue;
+        }
+        let i = *index.entry(fields[0].to_string()).or_insert_with(|| {
+            res.push((fields[0].to_string(), Vec::new()));
+            res.len() - 1
+        });
+        res[i].1.push(Segment {
+            haplotype: fields[1].to_string(),
+            walk,
+        });
+    }
+    Ok(res)
+}
+
+// distinguishable colors, spaced by the golden angle on the color wheel
+fn color(i: usize) -> String {
+    format!("hsl({:.0},65%,55%)", (i as f64 * 137.508) % 360.0)
+}
+
+fn write_svg<W: io::Write>(
+    founders: &[(String, Vec<Segment>)],
+    names: &ff::SegmentNames,
+    node_width: f64,
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
+    // positions are aligned by node identifier, so that the same node appears in the same column
+    // in all founders
+    let mut nodes: Vec<u64> = founders
+        .iter()
+        .flat_map(|(_, f)| {
+            f.iter()
+                .flat_map(|s| s.walk.iter().map(|v| v.unpack_number()))
+        })
+        .collect();
+    nodes.sort_unstable();
+    nodes.dedup();
+    let column: FxHashMap<u64, usize> = nodes.iter().enumerate().map(|(i, &v)| (v, i)).collect();
+
+    let mut haplotypes: Vec<&str> = Vec::new();
+    for (_, f) in founders.iter() {
+        for s in f.iter() {
+            if !haplotypes.contains(&s.haplotype.as_str()) {
+                haplotypes.push(&s.haplotype);
+            }
+        }
+    }
+    let hap_color: FxHashMap<&str, String> = haplotypes
+        .iter()
+        .enumerate()
+        .map(|(i, &h)| (h, color(i)))
+        .collect();
+
+    let left = MARGIN
+        + CHAR_WIDTH
+            * founders
+                .iter()
+                .map(|(name, _)| name.len())
+                .max()
+                .unwrap_or(0) as f64
+        + MARGIN;
+    let width = left + nodes.len() as f64 * node_width + MARGIN;
+    let tracks_bottom =
+        AXIS_HEIGHT + founders.len() as f64 * (TRACK_HEIGHT + TRACK_GAP) + TRACK_GAP;
+    let per_row = ((width - 2.0 * MARGIN) / LEGEND_WIDTH).floor().max(1.0) as usize;
+    let legend_rows = (haplotypes.len() + per_row - 1) / per_row;
+    let height = tracks_bottom + legend_rows as f64 * (TRACK_HEIGHT + TRACK_GAP) + MARGIN;
+    let x = |v: &Handle| left + column[&v.unpack_number()] as f64 * node_width;
+
+    writeln!(
+        out,
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{:.1}\" height=\"{:.1}\" font-family=\"monospace\" font-size=\"11\">",
+        width, height
+    )?;
+
+    // axis with about 20 ticks, labeled by segment names
+    let tick = (nodes.len() / 20).max(1);
+    writeln!(
+        out,
+        "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/>",
+        left,
+        AXIS_HEIGHT - 4.0,
+        left + nodes.len() as f64 * node_width,
+        AXIS_HEIGHT - 4.0
+    )?;
+    for (i, &v) in nodes.iter().enumerate().step_by(tick) {
+        let xi = left + (i as f64 + 0.5) * node_width;
+        writeln!(
+            out,
+            "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\"/><text x=\"{:.1}\" y=\"{:.1}\" text-anchor=\"middle\">{}</text>",
+            xi,
+            AXIS_HEIGHT - 8.0,
+            xi,
+            AXIS_HEIGHT - 4.0,
+            xi,
+            AXIS_HEIGHT - 12.0,
+            ff::escape_xml(&names.name(v as usize))
+        )?;
+    }
+
+    for (r, (name, f)) in founders.iter().enumerate() {
+        let y = AXIS_HEIGHT + TRACK_GAP + r as f64 * (TRACK_HEIGHT + TRACK_GAP);
+        writeln!(
+            out,
+            "<text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            MARGIN,
+            y + TRACK_HEIGHT - 3.0,
+            ff::escape_xml(name)
+        )?;
+        for s in f.iter() {
+            let c = &hap_color[s.haplotype.as_str()];
+            // runs of steps through adjacent columns are drawn as a single box, jumps over other
+            // nodes (e.g., alternative alleles) as a thin line
+            let mut start = 0;
+            for k in 1..=s.walk.len() {
+                let jump = k < s.walk.len() && {
+                    let (a, b) = (
+                        column[&s.walk[k - 1].unpack_number()],
+                        column[&s.walk[k].unpack_number()],
+                    );
+                    a.max(b) - a.min(b) != 1
+                };
+                if k < s.walk.len() && !jump {
+                    continue;
+                }
+                let (x1, x2) = s.walk[start..k]
+                    .iter()
+                    .map(x)
+                    .fold((f64::MAX, f64::MIN), |(lo, hi), xi| {
+                        (lo.min(xi), hi.max(xi))
+                    });
+                writeln!(
+                    out,
+                    "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"><title>{} {}: {}</title></rect>",
+                    x1,
+                    y,
+                    x2 - x1 + node_width,
+                    TRACK_HEIGHT,
+                    c,
+                    ff::escape_xml(name),
+                    ff::escape_xml(&s.haplotype),
+                    ff::escape_xml(&names.v2seq(&s.walk[start..k], ""))
+                )?;
+                if jump {
+                    writeln!(
+                        out,
+                        "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"{}\" stroke-width=\"2\"/>",
+                        x(&s.walk[k - 1]) + node_width / 2.0,
+                        y + TRACK_HEIGHT / 2.0,
+                        x(&s.walk[k]) + node_width / 2.0,
+                        y + TRACK_HEIGHT / 2.0,
+                        c
+                    )?;
+                }
+                start = k;
+            }
+        }
+        // recombinations take place at the node shared by consecutive segments
+        for (a, b) in f.iter().zip(f.iter().skip(1)) {
+            let v = b.walk[0];
+            let xv = x(&v) + node_width / 2.0;
+            writeln!(
+                out,
+                "<line x1=\"{:.1}\" y1=\"{:.1}\" x2=\"{:.1}\" y2=\"{:.1}\" stroke=\"black\" stroke-width=\"2\"><title>{}: recombination {} / {} at {}</title></line>",
+                xv,
+                y - 3.0,
+                xv,
+                y + TRACK_HEIGHT + 3.0,
+                ff::escape_xml(name),
+                ff::escape_xml(&a.haplotype),
+                ff::escape_xml(&b.haplotype),
+                ff::escape_xml(&names.v2str(&v))
+            )?;
+        }
+    }
+
+    for (i, h) in haplotypes.iter().enumerate() {
+        let xi = MARGIN + (i % per_row) as f64 * LEGEND_WIDTH;
+        let yi = tracks_bottom + (i / per_row) as f64 * (TRACK_HEIGHT + TRACK_GAP);
+        writeln!(
+            out,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"{}\"/><text x=\"{:.1}\" y=\"{:.1}\">{}</text>",
+            xi,
+            yi,
+            TRACK_HEIGHT,
+            TRACK_HEIGHT,
+            hap_color[h],
+            xi + TRACK_HEIGHT + 4.0,
+            yi + TRACK_HEIGHT - 3.0,
+            ff::escape_xml(h)
+        )?;
+    }
+    writeln!(out, "</svg>")
+}
+
+fn main() -> Result<(), io::Error> {
+    env_logger::init();
+
+    // initialize command line parser & parse command line arguments
+    let params = Command::parse();
+
+    let names = match &params.graph {
+        None => ff::SegmentNames::default(),
+        Some(g) => {
+            log::info!("reading segment names from graph {}", g);
+            ff::read_segment_names(g)?
+        }
+    };
+
+    log::info!("reading founder mosaics from {}", &params.mosaics);
+    let founders = read_mosaics(&params.mosaics, &names)?;
+    log::info!(
+        "drawing {} founders with {} recombinations",
+        founders.len(),
+        founders.iter().map(|(_, f)| f.len() - 1).sum::<usize>()
+    );
+
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+    if params.html {
+        writeln!(
+            out,
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{}</title>\n</head>\n<body>",
+            ff::escape_xml(&params.mosaics)
+        )?;
+    }
+    write_svg(&founders, &names, params.node_width, &mut out)?;
+    if params.html {
+        writeln!(out, "</body>\n</html>")?;
+    }
+    ff::finish(out)?;
+
+    log::info!("done");
+    Ok(())
+}
@@ -0,0 +1,279 @@
+/* standard use */
+use std::io::{self, BufRead, Write};
+
+/* crate use */
+use clap::Parser;
+use handlegraph::{handle::Handle, handlegraph::*, hashgraph::HashGraph};
+use regex::Regex;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/* private use */
+use founderset as ff;
+
+#[derive(clap::Parser, Debug)]
+#[clap(
+    version = "0.1",
+    author = "Daniel Doerr <daniel.doerr@hhu.de>",
+    about = "Stitch founder mosaics of overlapping windows into mosaics along the whole reference"
+)]
+pub struct Command {
+    #[clap(
+        short = 'g',
+        long = "graph",
+        help = "Graph in GFA1 format from which windows were extracted",
+        required = true
+    )]
+    pub graph: String,
+
+    #[clap(
+        index = 1,
+        help = "Founder mosaics of each window in long format of min2seq, in order along the reference",
+        required = true,
+        min_values = 1
+    )]
+    pub windows: Vec<String>,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
+}
+
+// founder walk along with the haplotype each step stems from; where the haplotype changes, the
+// step is shared by both haplotypes, i.e., a recombination takes place at its node
+#[derive(Clone, Debug, Default)]
+struct Mosaic {
+    walk: Vec<Handle>,
+    haplotypes: Vec<String>,
+}
+
+impl Mosaic {
+    fn reverse(&mut self) {
+        // recombination nodes are labeled by the haplotype that follows, so labels shift by one
+        let n = self.walk.len();
+        let mut haplotypes = Vec::with_capacity(n);
+        for k in (0..n).rev() {
+            if k > 0 && self.haplotypes[k - 1] != self.haplotypes[k] {
+                haplotypes.push(self.haplotypes[k - 1].clone());
+            } else {
+                haplotypes.push(self.haplotypes[k].clone());
+            }
+        }
+        self.walk = self.walk.iter().rev().map(|v| v.flip()).collect();
+        self.haplotypes = haplotypes;
+    }
+
+    fn labels(&self) -> FxHashSet<(u64, &str)> {
+        self.walk
+            .iter()
+            .zip(self.haplotypes.iter())
+            .map(|(v, h)| (v.unpack_number(), h.as_str()))
+            .collect()
+    }
+
+    fn recombinations(&self) -> usize {
+        self.haplotypes.windows(2).filter(|x| x[0] != x[1]).count()
+    }
+}
+
+// reads mosaics in long format, i.e., lines of founder index, haplotype name, and walk, where
+// consecutive lines of the same founder share their boundary node; nodes added by subgr (which
+// are not part of the graph) are dropped, haplotype names are stripped of the coordinates of
+// their sub-walk, and mosaics are oriented along the reference
+fn read_mosaics(
+    file: &str,
+    graph: &HashGraph,
+    names: &ff::SegmentNames,
+) -> Result<Vec<Mosaic>, io::Error> {
+    let coords = Regex::new(r":\d+-\d+$").unwrap();
+    let mut res: Vec<Mosaic> = Vec::new();
+    let mut index: FxHashMap<String, usize> = FxHashMap::default();
+
+    for line_op in ff::open_file(file)?.lines() {
+        let line = line_op?;
+        let fields: Vec<&str> = line.split('\t').collect();
+        if fields.len() < 3 {
+            continue;
+        }
+        let walk = ff::parse_walk_lenient(&fields[1..].join("\t"), names)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let haplotype = coords.replace(fields[1], "").to_string();
+        let i = *index.entry(fields[0].to_string()).or_insert_with(|| {
+            res.push(Mosaic::default());
+            res.len() - 1
+        });
+        let m = &mut res[i];
+        for (k, v) in walk.into_iter().enumerate() {
+            if !graph.has_node(v.id()) {
+                continue;
+            }
+            if k == 0 && m.walk.last() == Some(&v) {
+                *m.haplotypes.last_mut().unwrap() = haplotype.clone();
+            } else {
+                m.walk.push(v);
+                m.haplotypes.push(haplotype.clone());
+            }
+        }
+    }
+
+    res.retain(|m| !m.walk.is_empty());
+    for m in res.iter_mut() {
+        // orientation is determined as in subgr, by the number of reversed nodes
+        if 2 * m.walk.iter().filter(|v| v.is_reverse()).count() > m.walk.len() {
+            m.reverse();
+        }
+    }
+    Ok(res)
+}
+
+// positions in a and b of the node at which b continues a; a junction at which the haplotype of
+// a carries on in b is preferred over the first common node
+fn find_junction(a: &Mosaic, b: &Mosaic) -> Option<(usize, usize)> {
+    let mut first = None;
+    for (j, v) in b.walk.iter().enumerate() {
+        if let Some(i) = a.walk.iter().rposition(|x| x == v) {
+            if i == 0 || a.haplotypes[i - 1] == b.haplotypes[j] {
+                return Some((i, j));
+            }
+            first = first.or(Some((i, j)));
+        }
+    }
+    first
+}
+
+// pairs of founders of consecutive windows, matched greedily by the number of nodes that they
+// share in the overlap of both windows while stemming from the same haplotype
+fn match_founders(tails: &[Mosaic], founders: &[Mosaic]) -> Vec<(usize, usize)> {
+    let mut candidates: Vec<(usize, usize, usize, usize)> = Vec::new();
+    for (i, a) in tails.iter().enumerate() {
+        let la = a.labels();
+        let na: FxHashSet<u64> = a.walk.iter().map(|v| v.unpack_number()).collect();
+        for (j, b) in founders.iter().enumerate() {
+            let lb = b.labels();
+            let nb: FxHashSet<u64> = b.walk.iter().map(|v| v.unpack_number()).collect();
+            let common = na.intersection(&nb).count();
+            if common > 0 {
+                candidates.push((la.intersection(&lb).count(), common, i, j));
+            }
+        }
+    }
+    candidates.sort_by(|x, y| (y.0, y.1, x.2, x.3).cmp(&(x.0, x.1, y.2, y.3)));
+
+    let mut res: Vec<(usize, usize)> = Vec::new();
+    let mut used_a: FxHashSet<usize> = FxHashSet::default();
+    let mut used_b: FxHashSet<usize> = FxHashSet::default();
+    for (_, _, i, j) in candidates {
+        if !used_a.contains(&i) && !used_b.contains(&j) {
+            used_a.insert(i);
+            used_b.insert(j);
+            res.push((i, j));
+        }
+    }
+    res
+}
+
+fn write_mosaics<W: io::Write>(
+    mosaics: &[Mosaic],
+    names: &ff::SegmentNames,
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
+    for (i, m) in mosaics.iter().enumerate() {
+        let mut start = 0;
+        for k in 1..=m.walk.len() {
+            if k == m.walk.len() || m.haplotypes[k] != m.haplotypes[start] {
+                // the recombination node closes the current segment and opens the next
+                let end = (k + 1).min(m.walk.len());
+                writeln!(
+                    out,
+                    "{}\t{}\t{}",
+                    i + 1,
+                    m.haplotypes[start],
+                    names.v2seq(&m.walk[start..end], "")
+                )?;
+                start = k;
+            }
+        }
+    }
+    Ok(())
+}
+
+fn main() -> Result<(), io::Error> {
+    env_logger::init();
+
+    // initialize command line parser & parse command line arguments
+    let params = Command::parse();
+
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
+
+    log::info!("loading graph {}", &params.graph);
+    let ff::StreamedGFA { graph, names, .. } =
+        ff::read_gfa_file(&params.graph, &ff::GFAReadOptions::default())?;
+
+    // stitched mosaics, along with the start position of the part contributed by the last window
+    let mut stitched: Vec<(Mosaic, usize)> = Vec::new();
+    // stitched mosaics that reach into the current window
+    let mut open: Vec<usize> = Vec::new();
+    let mut junction_recombinations = 0;
+
+    for file in params.windows.iter() {
+        log::info!("reading founder mosaics of window {}", file);
+        let founders = read_mosaics(file, &graph, &names)?;
+        let tails: Vec<Mosaic> = open
+            .iter()
+            .map(|&c| {
+                let (m, offset) = &stitched[c];
+                Mosaic {
+                    walk: m.walk[*offset..].to_vec(),
+                    haplotypes: m.haplotypes[*offset..].to_vec(),
+                }
+            })
+            .collect();
+
+        let mut joined: FxHashMap<usize, usize> = FxHashMap::default();
+        for (i, j) in match_founders(&tails, &founders) {
+            if let Some((x, y)) = find_junction(&tails[i], &founders[j]) {
+                let (m, offset) = &mut stitched[open[i]];
+                let pos = *offset + x;
+                if pos > 0 && m.haplotypes[pos - 1] != founders[j].haplotypes[y] {
+                    junction_recombinations += 1;
+                }
+                m.walk.truncate(pos);
+                m.haplotypes.truncate(pos);
+                m.walk.extend_from_slice(&founders[j].walk[y..]);
+                m.haplotypes.extend_from_slice(&founders[j].haplotypes[y..]);
+                *offset = pos;
+                joined.insert(j, open[i]);
+            }
+        }
+        log::info!(
+            "continued {} of {} founders from previous window",
+            joined.len(),
+            founders.len()
+        );
+
+        open = (0..founders.len())
+            .map(|j| match joined.get(&j) {
+                Some(&c) => c,
+                None => {
+                    stitched.push((founders[j].clone(), 0));
+                    stitched.len() - 1
+                }
+            })
+            .collect();
+    }
+
+    let mosaics: Vec<Mosaic> = stitched.into_iter().map(|(m, _)| m).collect();
+    log::info!(
+        "stitched {} founders with {} recombinations, {} of which at window junctions",
+        mosaics.len(),
+        mosaics.iter().map(|m| m.recombinations()).sum::<usize>(),
+        junction_recombinations
+    );
+    write_mosaics(&mosaics, &names, &mut out)?;
+    ff::finish(out)?;
+
+    log::info!("done");
+    Ok(())
+}
@@ -1,19 +1,17 @@
 /* standard use */
 use std::io;
-use std::io::prelude::*;
-use std::str;
+use std::str::{self, FromStr};
 
 /* crate use */
 use clap::Parser;
-use gfa::{gfa::GFA, parser::GFAParser};
 use handlegraph::{
     handle::{Edge, Handle},
     hashgraph::HashGraph,
     mutablehandlegraph::AdditiveHandleGraph,
-    pathhandlegraph::{GraphPathNames, PathId},
+    pathhandlegraph::{embedded_paths::MutableGraphPaths, GraphPathNames, PathId},
 };
 use regex::Regex;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
 
 /* private use */
 use founderset as ff;
@@ -35,6 +33,34 @@ pub struct Command {
     )]
     pub paths_only: String,
 
+    #[clap(
+        short = 's',
+        long = "samples",
+        help = "Only report subgraph induced by paths whose PanSN sample (\"sample#hap#contig\") is listed in given file (one per line)"
+    )]
+    pub samples: Option<String>,
+
+    #[clap(
+        short = 'H',
+        long = "haplotype",
+        help = "Only report subgraph induced by paths with given PanSN haplotype index"
+    )]
+    pub haplotype: Option<usize>,
+
+    #[clap(
+        short = 'c',
+        long = "contig",
+        help = "Only report subgraph induced by paths with given PanSN contig name"
+    )]
+    pub contig: Option<String>,
+
+    #[clap(
+        short = 'R',
+        long = "region",
+        help = "Only report subgraph induced by the sub-walks of selected paths that span given region of a reference path (\"path:start-end\", 1-based and inclusive), widened to segments traversed by all selected paths"
+    )]
+    pub region: Option<String>,
+
     #[clap(
         short = 'l',
         long = "length",
@@ -42,11 +68,16 @@ pub struct Command {
         default_value = "1"
     )]
     pub min_length: usize,
+
+    #[clap(
+        long = "compress",
+        help = "Compress output with given method (gzip or zstd)"
+    )]
+    pub compress: Option<ff::Compression>,
 }
 
 fn identify_traversable_subgraph(
     graph: &HashGraph,
-    re_path_names: &Regex,
     min_length: &usize,
 ) -> (
     FxHashSet<Handle>,
@@ -62,9 +93,7 @@ fn identify_traversable_subgraph(
     for path_id in graph.paths.keys() {
         let path_name_vec = graph.get_path_name_vec(*path_id).unwrap();
         let path_name = str::from_utf8(&path_name_vec[..]).unwrap();
-        if &graph.get_path(path_id).unwrap().len() >= min_length
-            && re_path_names.is_match(&path_name)
-        {
+        if &graph.get_path(path_id).unwrap().len() >= min_length {
             let path = graph.get_path(path_id).unwrap();
             nodes.extend(path.nodes.iter().map(|x| x.forward()));
             for i in 0..path.nodes.len() - 1 {
@@ -106,16 +135,196 @@ fn identify_traversable_subgraph(
     (nodes, source_sinks, edges, paths)
 }
 
+// reference path name and 0-based, half-open interval of a region given as "path:start-end" with
+// 1-based, inclusive coordinates
+fn parse_region(region: &str) -> Result<(String, usize, usize), String> {
+    let err = || {
+        format!(
+            "invalid region \"{}\", expected format is \"path:start-end\"",
+            region
+        )
+    };
+    let (path, coords) = region.rsplit_once(':').ok_or_else(err)?;
+    let (start, end) = coords.split_once('-').ok_or_else(err)?;
+    let start = usize::from_str(&start.replace(',', "")).map_err(|_| err())?;
+    let end = usize::from_str(&end.replace(',', "")).map_err(|_| err())?;
+    if start == 0 || start > end {
+        return Err(err());
+    }
+    Ok((path.to_string(), start - 1, end))
+}
+
+// replaces all paths of the graph by their sub-walks between two segments of the reference path
+// that enclose the region and are traversed by all paths, so that the sub-walks of all paths span
+// the region even if they deviate from the reference at its boundaries (e.g., at variant alleles);
+// sub-walks traversing the region in reverse direction are reversed and all sub-walks are named
+// "path:start-end" by their 0-based, half-open coordinates on the original path
+fn extract_region(
+    graph: &mut HashGraph,
+    region: &str,
+    include_reference: bool,
+    lengths: &FxHashMap<usize, usize>,
+    annotations: &mut ff::GFAAnnotations,
+    names: &ff::SegmentNames,
+) -> Result<(), String> {
+    let (ref_name, start, end) = parse_region(region)?;
+    let ref_id = graph
+        .get_path_id(ref_name.as_bytes())
+        .ok_or(format!("reference path {} not found", ref_name))?;
+    let ref_walk = graph.get_path(&ref_id).unwrap().nodes.clone();
+    let offsets = ff::step_offsets(&ref_walk, lengths, &annotations.overlaps);
+    let overlapping: Vec<usize> = (0..ref_walk.len())
+        .filter(|&k| offsets[k] < end && offsets[k + 1] > start)
+        .collect();
+    if *offsets.last().unwrap() == 0 {
+        return Err(format!(
+            "reference path {} has length 0, segments need sequences or LN tags",
+            ref_name
+        ));
+    }
+    if overlapping.is_empty() {
+        return Err(format!(
+            "region {} lies outside of reference path {} of length {}",
+            region,
+            ref_name,
+            offsets.last().unwrap()
+        ));
+    }
+    let (first, last) = (overlapping[0], *overlapping.last().unwrap());
+    log::info!(
+        "region {} spans segments {} to {} of reference path",
+        region,
+        names.v2str(&ref_walk[first]),
+        names.v2str(&ref_walk[last])
+    );
+
+    // segments traversed by all paths, irrespective of orientation
+    let mut traversals: FxHashMap<u64, usize> = FxHashMap::default();
+    for path in graph.paths.values() {
+        let nodes: FxHashSet<u64> = path.nodes.iter().map(|v| v.unpack_number()).collect();
+        nodes
+            .into_iter()
+            .for_each(|v| *traversals.entry(v).or_insert(0) += 1);
+    }
+    let shared = |v: &Handle| traversals.get(&v.unpack_number()) == Some(&graph.paths.len());
+    let a = match (0..=first).rev().find(|&k| shared(&ref_walk[k])) {
+        Some(k) => ref_walk[k],
+        None => {
+            log::warn!(
+                "none of the segments of reference path {} up to the region is traversed by all paths",
+                ref_name
+            );
+            ref_walk[first]
+        }
+    };
+    let b = match (last..ref_walk.len()).find(|&k| shared(&ref_walk[k])) {
+        Some(k) => ref_walk[k],
+        None => {
+            log::warn!(
+                "none of the segments of reference path {} from the region on is traversed by all paths",
+                ref_name
+            );
+            ref_walk[last]
+        }
+    };
+    log::info!(
+        "extracting sub-walks between segments {} and {}",
+        names.v2str(&a),
+        names.v2str(&b)
+    );
+
+    // sub-walks start and end at the anchors' segments in either orientation, e.g., if a path
+    // traverses an anchor in reverse due to an inversion
+    let (a, b) = (a.unpack_number(), b.unpack_number());
+    let mut sub_walks: Vec<(Vec<u8>, Vec<Handle>)> = Vec::new();
+    for path_id in graph.paths.keys() {
+        let path = graph.get_path(path_id).unwrap();
+        if path.name == ref_name.as_bytes() && !include_reference {
+            continue;
+        }
+        let walk = &path.nodes;
+        let offsets = ff::step_offsets(walk, lengths, &annotations.overlaps);
+        let n = sub_walks.len();
+        let mut k = 0;
+        while k < walk.len() {
+            let end = match walk[k].unpack_number() {
+                v if v == a => b,
+                v if v == b => a,
+                _ => {
+                    k += 1;
+                    continue;
+                }
+            };
+            match (k..walk.len()).find(|&l| walk[l].unpack_number() == end) {
+                None => break,
+                Some(l) => {
+                    let mut sub_walk = walk[k..=l].to_vec();
+                    if walk[k].unpack_number() != a {
+                        sub_walk = sub_walk.iter().rev().map(|v| v.flip()).collect();
+                    }
+                    let mut name = path.name.clone();
+                    name.extend(format!(":{}-{}", offsets[k], offsets[l + 1]).bytes());
+                    if let Some(tags) = annotations.path_tags.get(&path.name).cloned() {
+                        annotations.path_tags.insert(name.clone(), tags);
+                    }
+                    sub_walks.push((name, sub_walk));
+                    k = l + 1;
+                }
+            }
+        }
+        if sub_walks.len() == n {
+            log::warn!(
+                "path {} does not span region {} and is dropped",
+                String::from_utf8_lossy(&path.name),
+                region
+            );
+        }
+    }
+    log::info!("extracted {} sub-walks spanning region", sub_walks.len());
+
+    // new path identifiers are derived from the number of paths, so all paths are removed before
+    // any sub-walk is added
+    for path_id in graph.paths.keys().copied().collect::<Vec<PathId>>() {
+        graph.destroy_path(path_id);
+    }
+    for (name, sub_walk) in sub_walks {
+        let path_id = graph
+            .create_path(&name, false)
+            .ok_or(format!("duplicate path {}", String::from_utf8_lossy(&name)))?;
+        for v in sub_walk {
+            graph.path_append_step(path_id, v);
+        }
+    }
+    Ok(())
+}
+
 fn add_source_sink(
     graph: &mut HashGraph,
     source_sinks: &FxHashSet<Handle>,
     subgraph_nodes: &mut FxHashSet<Handle>,
     subgraph_edges: &mut FxHashSet<Edge>,
+    names: &mut ff::SegmentNames,
 ) {
     let integrator = graph.append_handle(b"*");
     let source = graph.append_handle(b"*");
     let sink = graph.append_handle(b"*");
 
+    // in graphs with named segments, the added nodes receive names that do not occur in the
+    // graph, so that they cannot be mistaken for any of its segments
+    if !names.is_identity() {
+        for (v, label) in [
+            (integrator, "integrator"),
+            (source, "source"),
+            (sink, "sink"),
+        ] {
+            let mut name = format!("subgr_{}", label);
+            while names.id(&name).is_ok() {
+                name.push('_');
+            }
+            assert_eq!(names.insert(&name), v.unpack_number() as usize);
+        }
+    }
+
     for v in source_sinks.iter() {
         let e = Edge(integrator, *v);
         graph.create_edge(e);
@@ -131,10 +340,10 @@ fn add_source_sink(
     subgraph_edges.insert(Edge(integrator.flip(), sink));
 
     log::info!(
-        "added source node >{}, sink node >{} and integrator node >{}",
-        source.unpack_number(),
-        sink.unpack_number(),
-        integrator.unpack_number()
+        "added source node {}, sink node {} and integrator node {}",
+        names.v2str(&source),
+        names.v2str(&sink),
+        names.v2str(&integrator)
     );
 
     subgraph_nodes.insert(integrator);
@@ -145,26 +354,74 @@ fn add_source_sink(
 fn main() -> Result<(), io::Error> {
     env_logger::init();
 
-    // print output to stdout
-    let mut out = io::BufWriter::new(std::io::stdout());
-
     // initialize command line parser & parse command line arguments
     let params = Command::parse();
 
-    log::info!("loading graph {}", &params.graph);
-    let parser = GFAParser::new();
-    let gfa: GFA<usize, ()> = parser.parse_file(&params.graph).unwrap();
-
-    log::info!("constructing handle graph");
-    let mut graph = HashGraph::from_gfa(&gfa);
+    // print output to stdout
+    let mut out = ff::compress(io::stdout(), params.compress)?;
 
     log::info!(
         "searching for paths matching regular expression \"{}\"",
         &params.paths_only
     );
     let re = Regex::new(&params.paths_only).unwrap();
+    let selection = ff::PanSNSelection {
+        samples: match &params.samples {
+            None => None,
+            Some(f) => {
+                log::info!("reading sample list {}", f);
+                Some(ff::read_sample_list(ff::open_file(f)?).unwrap())
+            }
+        },
+        haplotype: params.haplotype,
+        contig: params.contig.clone(),
+    };
+    if !selection.is_empty() {
+        log::info!("selecting paths by PanSN name: {:?}", selection);
+    }
+    let select = |name: &str| re.is_match(name) && selection.matches(&ff::parse_pansn(name));
+    let ref_name = match &params.region {
+        None => None,
+        Some(r) => Some(
+            parse_region(r)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+                .0,
+        ),
+    };
+    let select_with_ref = |name: &str| select(name) || ref_name.as_deref() == Some(name);
+
+    log::info!("loading graph {}", &params.graph);
+    let ff::StreamedGFA {
+        mut graph,
+        lengths,
+        mut names,
+        mut annotations,
+    } = ff::read_gfa_file(
+        &params.graph,
+        &ff::GFAReadOptions {
+            sequences: true,
+            lengths: params.region.is_some(),
+            paths: Some(&select_with_ref),
+            tags: true,
+        },
+    )?;
+
+    if let (Some(region), Some(ref_name)) = (&params.region, &ref_name) {
+        log::info!("extracting region {}", region);
+        extract_region(
+            &mut graph,
+            region,
+            select(ref_name),
+            &lengths,
+            &mut annotations,
+            &names,
+        )
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+    }
+
     let (mut nodes, source_sinks, mut edges, paths) =
-        identify_traversable_subgraph(&graph, &re, &params.min_length);
+        identify_traversable_subgraph(&graph, &params.min_length);
+    log::info!("selected {} paths", paths.len());
 
     log::info!(
         "identified {} source/sinks: {}",
@@ -172,11 +429,25 @@ fn main() -> Result<(), io::Error> {
         ff::hv2seq(&source_sinks, ", ")
     );
 
-    add_source_sink(&mut graph, &source_sinks, &mut nodes, &mut edges);
+    add_source_sink(
+        &mut graph,
+        &source_sinks,
+        &mut nodes,
+        &mut edges,
+        &mut names,
+    );
 
     log::info!("printing subgraph induced by path selection");
-    ff::write_subset_gfa(&graph, &nodes, &edges, &paths, &mut out)?;
-    out.flush()?;
+    ff::write_subset_gfa(
+        &graph,
+        &nodes,
+        &edges,
+        &paths,
+        &names,
+        &annotations,
+        &mut out,
+    )?;
+    ff::finish(out)?;
 
     log::info!("done");
     Ok(())
@@ -0,0 +1,239 @@
+/* standard use */
+use std::cmp::Reverse;
+
+/* crate use */
+use handlegraph::{
+    handle::{Direction, Handle},
+    handlegraph::*,
+    hashgraph::HashGraph,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+// superbubble of the bidirected graph: all walks leaving the entry handle pass through the
+// interior nodes until they reach the exit handle, and the interior is not connected to the rest
+// of the graph otherwise
+#[derive(Clone, Debug)]
+pub struct Bubble {
+    pub entry: Handle,
+    pub exit: Handle,
+    // node identifiers, sorted
+    pub interior: Vec<u64>,
+    // index of the innermost bubble that contains this one in its interior
+    pub parent: Option<usize>,
+    pub depth: usize,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct BubbleDecomposition {
+    // sorted by entry and exit handle, so that indices are reproducible for the same graph
+    pub bubbles: Vec<Bubble>,
+    // nodes whose removal disconnects the graph; walks are split into independent regions there
+    pub cut_nodes: FxHashSet<u64>,
+    innermost: FxHashMap<u64, usize>,
+    // bubbles whose entry or exit is the node, sorted
+    bounded: FxHashMap<u64, Vec<usize>>,
+}
+
+impl BubbleDecomposition {
+    // index of the innermost bubble that contains the node in its interior
+    pub fn bubble_of(&self, node: u64) -> Option<usize> {
+        self.innermost.get(&node).copied()
+    }
+
+    // indices of the bubbles that the node enters or exits; nodes shared by the walks through
+    // neighboring bubbles, such as recombination nodes, are often not in any bubble's interior
+    pub fn bubbles_bounded_by(&self, node: u64) -> &[usize] {
+        self.bounded.get(&node).map(|x| &x[..]).unwrap_or(&[])
+    }
+
+    pub fn is_cut_node(&self, node: u64) -> bool {
+        self.cut_nodes.contains(&node)
+    }
+
+    pub fn split_at_cut_nodes(&self, walk: &[Handle]) -> Vec<Vec<Handle>> {
+        split_at_cut_nodes(walk, &self.cut_nodes)
+    }
+}
+
+// splits a walk at the given cut nodes, which end one sub-walk and start the next
+pub fn split_at_cut_nodes(walk: &[Handle], cut_nodes: &FxHashSet<u64>) -> Vec<Vec<Handle>> {
+    let mut res: Vec<Vec<Handle>> = vec![Vec::new()];
+    for (i, v) in walk.iter().enumerate() {
+        res.last_mut().unwrap().push(*v);
+        if i > 0 && i + 1 < walk.len() && cut_nodes.contains(&v.unpack_number()) {
+            res.push(vec![*v]);
+        }
+    }
+    res
+}
+
+// exit and interior of the superbubble with the given entry, if any, following the algorithm of
+// Onodera et al. (2013) on the handles of the graph
+fn find_superbubble(graph: &HashGraph, s: Handle) -> Option<(Handle, Vec<u64>)> {
+    let mut visited: FxHashSet<Handle> = FxHashSet::default();
+    let mut seen: FxHashSet<Handle> = FxHashSet::default();
+    let mut stack: Vec<Handle> = vec![s];
+    seen.insert(s);
+
+    while let Some(v) = stack.pop() {
+        visited.insert(v);
+        seen.remove(&v);
+        let children: Vec<Handle> = graph.neighbors(v, Direction::Right).collect();
+        if children.is_empty() {
+            // tip
+            return None;
+        }
+        for u in children {
+            if u.id() == s.id() {
+                // cycle or inversion through the entry
+                return None;
+            }
+            seen.insert(u);
+            if graph
+                .neighbors(u, Direction::Left)
+                .all(|p| visited.contains(&p))
+            {
+                stack.push(u);
+            }
+        }
+        if stack.len() == 1 && seen.len() == 1 && seen.contains(&stack[0]) {
+            let t = stack.pop().unwrap();
+            if visited.contains(&t.flip()) || graph.neighbors(t, Direction::Right).any(|x| x == s) {
+                return None;
+            }
+            visited.remove(&s);
+            let mut interior: Vec<u64> = visited.iter().map(|x| x.unpack_number()).collect();
+            interior.sort_unstable();
+            interior.dedup();
+            return Some((t, interior));
+        }
+    }
+    None
+}
+
+// nodes whose removal disconnects their component of the graph, ignoring edge orientation
+pub fn articulation_points(graph: &HashGraph) -> FxHashSet<u64> {
+    let neighbors = |v: u64| {
+        let h = Handle::pack(v, false);
+        let mut res: Vec<u64> = graph
+            .neighbors(h, Direction::Left)
+            .chain(graph.neighbors(h, Direction::Right))
+            .map(|x| x.unpack_number())
+            .filter(|&x| x != v)
+            .collect();
+        res.sort_unstable();
+        res.dedup();
+        res
+    };
+
+    let mut res: FxHashSet<u64> = FxHashSet::default();
+    let mut disc: FxHashMap<u64, usize> = FxHashMap::default();
+    let mut low: FxHashMap<u64, usize> = FxHashMap::default();
+    let mut parent: FxHashMap<u64, u64> = FxHashMap::default();
+
+    let mut nodes: Vec<u64> = graph.handles().map(|v| v.unpack_number()).collect();
+    nodes.sort_unstable();
+    for root in nodes {
+        if disc.contains_key(&root) {
+            continue;
+        }
+        disc.insert(root, disc.len());
+        low.insert(root, disc[&root]);
+        let mut root_children = 0;
+        // iterative depth-first search, keeping the index of the next neighbor to explore
+        let mut stack: Vec<(u64, Vec<u64>, usize)> = vec![(root, neighbors(root), 0)];
+        while let Some((v, nv, i)) = stack.last_mut() {
+            let v = *v;
+            if *i < nv.len() {
+                let w = nv[*i];
+                *i += 1;
+                if !disc.contains_key(&w) {
+                    parent.insert(w, v);
+                    disc.insert(w, disc.len());
+                    low.insert(w, disc[&w]);
+                    if v == root {
+                        root_children += 1;
+                    }
+                    stack.push((w, neighbors(w), 0));
+                } else if parent.get(&v) != Some(&w) {
+                    low.insert(v, low[&v].min(disc[&w]));
+                }
+            } else {
+                stack.pop();
+                if let Some(&p) = parent.get(&v) {
+                    low.insert(p, low[&p].min(low[&v]));
+                    if p != root && low[&v] >= disc[&p] {
+                        res.insert(p);
+                    }
+                }
+            }
+        }
+        if root_children > 1 {
+            res.insert(root);
+        }
+    }
+    res
+}
+
+// decomposes the graph into (nested) superbubbles and identifies its cut nodes; each superbubble
+// is reported once, in the orientation whose entry handle is smaller, and superbubbles without
+// interior (i.e., single edges) are omitted
+pub fn decompose_bubbles(graph: &HashGraph) -> BubbleDecomposition {
+    let mut found: FxHashMap<(Handle, Handle), Vec<u64>> = FxHashMap::default();
+    for v in graph.handles() {
+        for s in [v, v.flip()] {
+            if let Some((t, interior)) = find_superbubble(graph, s) {
+                if !interior.is_empty() {
+                    let key = if s.as_integer() <= t.flip().as_integer() {
+                        (s, t)
+                    } else {
+                        (t.flip(), s.flip())
+                    };
+                    found.insert(key, interior);
+                }
+            }
+        }
+    }
+
+    let mut bubbles: Vec<Bubble> = found
+        .into_iter()
+        .map(|((entry, exit), interior)| Bubble {
+            entry,
+            exit,
+            interior,
+            parent: None,
+            depth: 0,
+        })
+        .collect();
+    bubbles.sort_by_key(|b| (b.entry.as_integer(), b.exit.as_integer()));
+
+    // superbubbles are either nested or disjoint in their interiors; visiting them from large to
+    // small, the innermost bubble recorded for any interior node so far is the enclosing one
+    let mut innermost: FxHashMap<u64, usize> = FxHashMap::default();
+    let mut order: Vec<usize> = (0..bubbles.len()).collect();
+    order.sort_by_key(|&i| (Reverse(bubbles[i].interior.len()), i));
+    for i in order {
+        if let Some(&p) = innermost.get(&bubbles[i].interior[0]) {
+            bubbles[i].parent = Some(p);
+            bubbles[i].depth = bubbles[p].depth + 1;
+        }
+        for &v in bubbles[i].interior.iter() {
+            innermost.insert(v, i);
+        }
+    }
+
+    let mut bounded: FxHashMap<u64, Vec<usize>> = FxHashMap::default();
+    for (i, b) in bubbles.iter().enumerate() {
+        bounded.entry(b.entry.unpack_number()).or_default().push(i);
+        if b.exit.unpack_number() != b.entry.unpack_number() {
+            bounded.entry(b.exit.unpack_number()).or_default().push(i);
+        }
+    }
+
+    BubbleDecomposition {
+        bubbles,
+        cut_nodes: articulation_points(graph),
+        innermost,
+        bounded,
+    }
+}
@@ -0,0 +1,130 @@
+/* standard use */
+use std::fmt;
+use std::fs;
+use std::io::{self, BufRead, Write};
+use std::str::FromStr;
+
+/* crate use */
+use flate2::{bufread::MultiGzDecoder, write::GzEncoder};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+}
+
+impl fmt::Display for Compression {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(match &self {
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        })?;
+        Ok(())
+    }
+}
+
+impl FromStr for Compression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "gzip" | "gz" => Ok(Compression::Gzip),
+            "zstd" | "zst" => Ok(Compression::Zstd),
+            _ => Err(format!(
+                "unknown compression \"{}\", supported are gzip and zstd",
+                s
+            )),
+        }
+    }
+}
+
+impl Compression {
+    // compression indicated by file extension, if any
+    pub fn from_path(file: &str) -> Option<Self> {
+        if file.ends_with(".gz") || file.ends_with(".bgz") {
+            Some(Compression::Gzip)
+        } else if file.ends_with(".zst") {
+            Some(Compression::Zstd)
+        } else {
+            None
+        }
+    }
+}
+
+// wraps input into a decoder if it starts with the magic bytes of gzip (which includes bgzip, a
+// series of gzip members) or zstd, otherwise passes it through
+pub fn decompress<R: io::Read + 'static>(
+    data: io::BufReader<R>,
+) -> Result<io::BufReader<Box<dyn io::Read>>, io::Error> {
+    let mut data = data;
+    let magic = data.fill_buf()?;
+    let res: Box<dyn io::Read> = if magic.starts_with(&[0x1f, 0x8b]) {
+        Box::new(MultiGzDecoder::new(data))
+    } else if magic.starts_with(&[0x28, 0xb5, 0x2f, 0xfd]) {
+        Box::new(zstd::Decoder::with_buffer(data)?)
+    } else {
+        Box::new(data)
+    };
+    Ok(io::BufReader::new(res))
+}
+
+// opens a plain or compressed file for reading
+pub fn open_file(file: &str) -> Result<io::BufReader<Box<dyn io::Read>>, io::Error> {
+    decompress(io::BufReader::new(fs::File::open(file)?))
+}
+
+// output that is written plain or through an encoder; encoders must be finished explicitly with
+// finish(), so that errors on writing the end of the stream are not lost
+pub enum Encoder<W: io::Write> {
+    Plain(W),
+    Gzip(GzEncoder<W>),
+    Zstd(zstd::Encoder<'static, W>),
+}
+
+impl<W: io::Write> io::Write for Encoder<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match self {
+            Encoder::Plain(w) => w.write(buf),
+            Encoder::Gzip(w) => w.write(buf),
+            Encoder::Zstd(w) => w.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match self {
+            Encoder::Plain(w) => w.flush(),
+            Encoder::Gzip(w) => w.flush(),
+            Encoder::Zstd(w) => w.flush(),
+        }
+    }
+}
+
+pub fn compress<W: io::Write + 'static>(
+    out: W,
+    compression: Option<Compression>,
+) -> Result<io::BufWriter<Encoder<W>>, io::Error> {
+    let res = match compression {
+        None => Encoder::Plain(out),
+        Some(Compression::Gzip) => {
+            Encoder::Gzip(GzEncoder::new(out, flate2::Compression::default()))
+        }
+        Some(Compression::Zstd) => Encoder::Zstd(zstd::Encoder::new(out, 0)?),
+    };
+    Ok(io::BufWriter::new(res))
+}
+
+// flushes buffered output and completes the compressed stream, if any
+pub fn finish<W: io::Write>(out: io::BufWriter<Encoder<W>>) -> Result<(), io::Error> {
+    let mut res = out.into_inner().map_err(|e| e.into_error())?;
+    match &mut res {
+        Encoder::Plain(_) => (),
+        Encoder::Gzip(w) => w.try_finish()?,
+        Encoder::Zstd(w) => w.do_finish()?,
+    }
+    res.flush()
+}
+
+// creates a file for writing, compressed as indicated by its extension
+pub fn create_file(file: &str) -> Result<io::BufWriter<Encoder<fs::File>>, io::Error> {
+    compress(fs::File::create(file)?, Compression::from_path(file))
+}
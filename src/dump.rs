@@ -0,0 +1,197 @@
+/* standard use */
+use std::io::{self, Write};
+use std::str::FromStr;
+
+/* crate use */
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/* private use */
+use crate::graph::EdgeType;
+
+#[derive(Clone, Debug)]
+pub struct DumpNode {
+    pub id: String,
+    pub label: String,
+    // index into the color palette, e.g., the haplotype a node is assigned to
+    pub color: Option<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub struct DumpEdge {
+    pub source: String,
+    pub target: String,
+    pub label: String,
+    pub style: EdgeType,
+    pub highlight: bool,
+}
+
+// intermediate graphs of the pipeline (flow graph of mkmin, component graph of flow2seq) for
+// inspection with graphviz or graph editors that read GraphML
+#[derive(Clone, Debug, Default)]
+pub struct GraphDump {
+    pub directed: bool,
+    pub nodes: Vec<DumpNode>,
+    pub edges: Vec<DumpEdge>,
+}
+
+// graphviz color scheme with the largest number of distinguishable colors
+const DOT_COLORSCHEME: &str = "set312";
+const DOT_COLORS: usize = 12;
+
+fn escape_dot(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+pub fn escape_xml(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+impl GraphDump {
+    pub fn write_dot<W: io::Write>(&self, out: &mut io::BufWriter<W>) -> Result<(), io::Error> {
+        let (kind, arrow) = if self.directed {
+            ("digraph", "->")
+        } else {
+            ("graph", "--")
+        };
+        writeln!(out, "{} G {{", kind)?;
+        writeln!(
+            out,
+            "    node [shape=box, colorscheme={}];",
+            DOT_COLORSCHEME
+        )?;
+        for v in self.nodes.iter() {
+            write!(
+                out,
+                "    \"{}\" [label=\"{}\"",
+                escape_dot(&v.id),
+                escape_dot(&v.label)
+            )?;
+            if let Some(c) = v.color {
+                write!(out, ", style=filled, fillcolor={}", c % DOT_COLORS + 1)?;
+            }
+            writeln!(out, "];")?;
+        }
+        for e in self.edges.iter() {
+            write!(
+                out,
+                "    \"{}\" {} \"{}\" [label=\"{}\", style={}",
+                escape_dot(&e.source),
+                arrow,
+                escape_dot(&e.target),
+                escape_dot(&e.label),
+                e.style
+            )?;
+            if e.highlight {
+                write!(out, ", color=red, penwidth=3")?;
+            }
+            writeln!(out, "];")?;
+        }
+        writeln!(out, "}}")
+    }
+
+    pub fn write_graphml<W: io::Write>(&self, out: &mut io::BufWriter<W>) -> Result<(), io::Error> {
+        writeln!(out, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(
+            out,
+            "<graphml xmlns=\"http://graphml.graphdrawing.org/xmlns\">"
+        )?;
+        writeln!(
+            out,
+            "  <key id=\"label\" for=\"all\" attr.name=\"label\" attr.type=\"string\"/>"
+        )?;
+        writeln!(
+            out,
+            "  <key id=\"color\" for=\"node\" attr.name=\"color\" attr.type=\"int\"/>"
+        )?;
+        writeln!(
+            out,
+            "  <key id=\"style\" for=\"edge\" attr.name=\"style\" attr.type=\"string\"/>"
+        )?;
+        writeln!(
+            out,
+            "  <key id=\"highlight\" for=\"edge\" attr.name=\"highlight\" attr.type=\"boolean\"/>"
+        )?;
+        writeln!(
+            out,
+            "  <graph id=\"G\" edgedefault=\"{}\">",
+            if self.directed {
+                "directed"
+            } else {
+                "undirected"
+            }
+        )?;
+        for v in self.nodes.iter() {
+            write!(
+                out,
+                "    <node id=\"{}\"><data key=\"label\">{}</data>",
+                escape_xml(&v.id),
+                escape_xml(&v.label)
+            )?;
+            if let Some(c) = v.color {
+                write!(out, "<data key=\"color\">{}</data>", c)?;
+            }
+            writeln!(out, "</node>")?;
+        }
+        for e in self.edges.iter() {
+            writeln!(
+                out,
+                "    <edge source=\"{}\" target=\"{}\"><data key=\"label\">{}</data><data key=\"style\">{}</data><data key=\"highlight\">{}</data></edge>",
+                escape_xml(&e.source),
+                escape_xml(&e.target),
+                escape_xml(&e.label),
+                e.style,
+                e.highlight
+            )?;
+        }
+        writeln!(out, "  </graph>")?;
+        writeln!(out, "</graphml>")
+    }
+
+    // writes the graph to <prefix>.dot and <prefix>.graphml
+    pub fn write_files(&self, prefix: &str) -> Result<(), io::Error> {
+        log::info!(
+            "writing graph with {} nodes and {} edges to {}.dot and {}.graphml",
+            self.nodes.len(),
+            self.edges.len(),
+            prefix,
+            prefix
+        );
+        let mut out = crate::create_file(&format!("{}.dot", prefix))?;
+        self.write_dot(&mut out)?;
+        crate::finish(out)?;
+        let mut out = crate::create_file(&format!("{}.graphml", prefix))?;
+        self.write_graphml(&mut out)?;
+        crate::finish(out)
+    }
+}
+
+// matching edges x_{u}_{v} and node colors c_{v}_{haplotype}_{i} chosen in a solution of the
+// recombination minimization model, with nodes given by their variable names
+pub fn min_solution_choices(
+    values: &[(String, f64)],
+) -> (FxHashSet<(String, String)>, FxHashMap<String, usize>) {
+    let mut matchings: FxHashSet<(String, String)> = FxHashSet::default();
+    let mut colors: FxHashMap<String, usize> = FxHashMap::default();
+
+    for (var, val) in values.iter() {
+        if val.round() != 1.0 {
+            continue;
+        }
+        let fields: Vec<&str> = var.split('_').collect();
+        match fields[..] {
+            ["x", u, v] => {
+                matchings.insert((u.to_string(), v.to_string()));
+            }
+            ["c", v, h, _] => {
+                if let Ok(h) = usize::from_str(h) {
+                    colors.insert(v.to_string(), h);
+                }
+            }
+            _ => (),
+        }
+    }
+    (matchings, colors)
+}
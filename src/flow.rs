@@ -1,11 +1,16 @@
 /* crate use */
-use handlegraph::handle::Handle;
+use handlegraph::{
+    handle::{self, Edge, Handle},
+    handlegraph::*,
+    hashgraph::HashGraph,
+};
+use itertools::Itertools;
 use quick_csv::Csv;
+use rand::Rng;
 use regex::Regex;
 use rustc_hash::{FxHashMap, FxHashSet};
 
 /* standard use */
-use std::fs;
 use std::io;
 use std::str::{self, FromStr};
 
@@ -103,25 +108,53 @@ impl Flow {
     }
 }
 
-pub fn read_flow(file: &String) -> Result<Flow, io::Error> {
-    let mut nodes: FxHashMap<(Extremity, Direction), usize> = FxHashMap::default();
-    let mut edges: FxHashMap<Extremity, FxHashSet<(Extremity, usize)>> = FxHashMap::default();
-
-    let pat_node = Regex::new(r"^(i|o)(\d+)(h|t)$").unwrap();
-    let pat_edge = Regex::new(r"^(i|o)(\d+)(h|t)_(i|o)(\d+)(h|t)$").unwrap();
+// reads variable assignments from a solution file, reporting malformed lines instead of panicking
+pub fn read_flow_values(file: &String) -> Result<Vec<(String, f64)>, io::Error> {
+    let mut res: Vec<(String, f64)> = Vec::new();
 
     log::info!("loading flow solution {}", &file);
-    let mut bf = io::BufReader::new(fs::File::open(&file)?);
+    let mut bf = crate::open_file(file)?;
     let reader = Csv::from_reader(&mut bf)
         .delimiter(b' ')
         .flexible(true)
         .has_header(true);
 
-    for row in reader.into_iter() {
-        let row = row.unwrap();
+    for (i, row) in reader.into_iter().enumerate() {
+        let row = row.map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
         let mut row_it = row.bytes_columns();
         let var = str::from_utf8(row_it.next().unwrap()).unwrap();
-        let val = usize::from_str(str::from_utf8(row_it.next().unwrap()).unwrap()).unwrap();
+        if var.is_empty() || var.starts_with('#') {
+            continue;
+        }
+        let val = row_it
+            .next()
+            .and_then(|x| f64::from_str(str::from_utf8(x).unwrap()).ok())
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!(
+                        "line {} of flow solution {} is malformed, file may be truncated",
+                        i + 2,
+                        file
+                    ),
+                )
+            })?;
+        res.push((var.to_string(), val));
+    }
+
+    Ok(res)
+}
+
+pub fn flow_from_values(values: &[(String, f64)]) -> Flow {
+    let mut nodes: FxHashMap<(Extremity, Direction), usize> = FxHashMap::default();
+    let mut edges: FxHashMap<Extremity, FxHashSet<(Extremity, usize)>> = FxHashMap::default();
+
+    let pat_node = Regex::new(r"^(i|o)(\d+)(h|t)$").unwrap();
+    let pat_edge = Regex::new(r"^(i|o)(\d+)(h|t)_(i|o)(\d+)(h|t)$").unwrap();
+
+    for (var, val) in values.iter() {
+        // solvers may report integral values with some numerical slack
+        let val = val.round() as usize;
 
         if let Some(m) = pat_edge.captures(var) {
             let u = Extremity {
@@ -164,7 +197,214 @@ pub fn read_flow(file: &String) -> Result<Flow, io::Error> {
         }
     }
 
-    Ok(Flow::new(nodes, edges))
+    Flow::new(nodes, edges)
+}
+
+pub fn read_flow(file: &String) -> Result<Flow, io::Error> {
+    Ok(flow_from_values(&read_flow_values(file)?))
+}
+
+// sources are all nodes that in default orientation have no edges to their left, sinks are all
+// nodes that in default orientation have no edges to their right
+pub fn graph_sources_sinks(graph: &HashGraph) -> (FxHashSet<Handle>, Vec<Handle>) {
+    let mut sources: FxHashSet<Handle> = FxHashSet::default();
+    let mut sinks: Vec<Handle> = Vec::new();
+    for v in graph.handles() {
+        if graph.degree(v, handle::Direction::Right) == 0 {
+            sinks.push(v);
+        } else if graph.degree(v, handle::Direction::Left) == 0 {
+            sources.insert(v);
+        }
+    }
+    (sources, sinks)
+}
+
+fn check_flow_value(var: &str, val: f64, violations: &mut Vec<String>) {
+    if val < 0. {
+        violations.push(format!("variable {} is negative ({})", var, val));
+    } else if (val - val.round()).abs() > 1e-6 {
+        violations.push(format!("variable {} is not integral ({})", var, val));
+    }
+}
+
+// checks the variable assignment of a flow solution as far as possible without the graph: values
+// must be non-negative and integral, edges must lead from out- to in-extremities, and the flow
+// must be conserved at each node, unless the solution has no edge entering (source) or leaving
+// (sink) it
+pub fn validate_flow_values(values: &[(String, f64)]) -> Vec<String> {
+    let mut violations: Vec<String> = Vec::new();
+
+    let pat_edge = Regex::new(r"^(i|o)(\d+)(h|t)_(i|o)(\d+)(h|t)$").unwrap();
+    // flow entering at and leaving from each extremity
+    let mut inflow: FxHashMap<(usize, char), f64> = FxHashMap::default();
+    let mut outflow: FxHashMap<(usize, char), f64> = FxHashMap::default();
+    for (var, val) in values.iter() {
+        check_flow_value(var, *val, &mut violations);
+        if let Some(m) = pat_edge.captures(var) {
+            if &m[1] != "o" || &m[4] != "i" {
+                violations.push(format!(
+                    "edge {} is not directed from o (out) to i (in)",
+                    var
+                ));
+                continue;
+            }
+            let ext = |i: usize, e: usize| {
+                (
+                    usize::from_str(&m[i]).unwrap(),
+                    m[e].chars().next().unwrap(),
+                )
+            };
+            *outflow.entry(ext(2, 3)).or_default() += val;
+            *inflow.entry(ext(5, 6)).or_default() += val;
+        }
+    }
+
+    // flow entering a node at one extremity leaves it at the other
+    for (&(id, e), f_in) in inflow.iter().sorted_by_key(|(x, _)| *x) {
+        let other = (id, if e == 'h' { 't' } else { 'h' });
+        if let Some(f_out) = outflow.get(&other) {
+            if (f_in - f_out).abs() > 1e-6 {
+                violations.push(format!(
+                    "flow conservation violated: {} enters at i{}{}, but {} leaves from o{}{}",
+                    f_in, id, e, f_out, other.0, other.1
+                ));
+            }
+        }
+    }
+
+    violations
+}
+
+// checks the variable assignment of a flow solution against the constraints of the linear
+// program generated by mkflow for the given graph and returns a description of each violation
+pub fn validate_flow(values: &[(String, f64)], graph: &HashGraph) -> Vec<String> {
+    let mut violations: Vec<String> = Vec::new();
+
+    let mut assignment: FxHashMap<&str, f64> = FxHashMap::default();
+    for (var, val) in values.iter() {
+        check_flow_value(var, *val, &mut violations);
+        assignment.insert(var.as_str(), *val);
+    }
+
+    // all variables of the linear program
+    let mut vars: FxHashSet<String> = FxHashSet::default();
+    for v in graph.handles() {
+        for x in [v, v.flip()] {
+            vars.insert(format!("i{}", v2extstr(&x)));
+            vars.insert(format!("o{}", v2extstr(&x)));
+        }
+    }
+    for Edge(u, v) in graph.edges() {
+        vars.insert(format!("o{}_i{}", v2extstr(&u), v2extstr(&v.flip())));
+        vars.insert(format!("o{}_i{}", v2extstr(&v.flip()), v2extstr(&u)));
+    }
+
+    for (var, _) in values.iter() {
+        if !vars.contains(var) {
+            violations.push(format!(
+                "variable {} does not correspond to any node or edge of the graph",
+                var
+            ));
+        }
+    }
+    // variables missing from the solution are taken to be zero
+    let val = |var: &String| assignment.get(var.as_str()).cloned().unwrap_or(0.);
+    for Edge(u, v) in graph.edges() {
+        let mut lhs = vec![format!("o{}_i{}", v2extstr(&u), v2extstr(&v.flip()))];
+        if u != v.flip() {
+            lhs.push(format!("o{}_i{}", v2extstr(&v.flip()), v2extstr(&u)));
+        }
+        if lhs.iter().map(val).sum::<f64>() < 1. - 1e-6 {
+            violations.push(format!(
+                "lower bound of edge {}{} violated: {} < 1",
+                v2str(&u),
+                v2str(&v),
+                lhs.join(" + ")
+            ));
+        }
+    }
+
+    let mut check = |lhs: Vec<String>, rhs: Vec<String>, what: &str| {
+        let l: f64 = lhs.iter().map(val).sum();
+        let r: f64 = rhs.iter().map(val).sum();
+        if (l - r).abs() > 1e-6 {
+            violations.push(if rhs.is_empty() {
+                format!("{} violated: {} = {} != 0", what, lhs.join(" + "), l)
+            } else {
+                format!(
+                    "{} violated: {} = {} != {} = {}",
+                    what,
+                    lhs.join(" + "),
+                    l,
+                    rhs.join(" + "),
+                    r
+                )
+            });
+        }
+    };
+
+    for v in graph.handles() {
+        for (x, side) in [
+            (v.flip(), handle::Direction::Left),
+            (v, handle::Direction::Right),
+        ] {
+            if graph.degree(v, side) == 0 {
+                continue;
+            }
+            let neighbors: Vec<Handle> = graph
+                .neighbors(v, side)
+                .map(|u| match side {
+                    handle::Direction::Left => u,
+                    handle::Direction::Right => u.flip(),
+                })
+                .collect();
+            check(
+                vec![format!("i{}", v2extstr(&x))],
+                neighbors
+                    .iter()
+                    .map(|u| format!("o{}_i{}", v2extstr(u), v2extstr(&x)))
+                    .collect(),
+                "capacity of in-flow",
+            );
+            check(
+                vec![format!("o{}", v2extstr(&x))],
+                neighbors
+                    .iter()
+                    .map(|u| format!("o{}_i{}", v2extstr(&x), v2extstr(u)))
+                    .collect(),
+                "capacity of out-flow",
+            );
+        }
+
+        check(
+            vec![format!("i{}", v2extstr(&v))],
+            vec![format!("o{}", v2extstr(&v.flip()))],
+            "flow conservation",
+        );
+        check(
+            vec![format!("i{}", v2extstr(&v.flip()))],
+            vec![format!("o{}", v2extstr(&v))],
+            "flow conservation",
+        );
+    }
+
+    let (sources, sinks) = graph_sources_sinks(graph);
+    for v in sinks.iter() {
+        check(
+            vec![format!("i{}", v2extstr(v))],
+            Vec::new(),
+            "sink constraint",
+        );
+    }
+    for v in sources.iter().sorted() {
+        check(
+            vec![format!("o{}", v2extstr(&v.flip()))],
+            Vec::new(),
+            "source constraint",
+        );
+    }
+
+    violations
 }
 
 pub fn extract_random_walk_from_flow(
@@ -207,3 +447,103 @@ pub fn extract_random_walk_from_flow(
 
     res
 }
+
+// walks along the flow starting at start, in each step choose() selects the successor among the
+// candidates, which are given in ascending order together with the last handle of the walk
+pub fn extract_walk_from_flow<F>(
+    edges: &mut FxHashMap<Extremity, FxHashSet<(Extremity, usize)>>,
+    start: &Extremity,
+    mut choose: F,
+) -> Vec<Handle>
+where
+    F: FnMut(&Handle, &[(Extremity, usize)]) -> usize,
+{
+    let mut res: Vec<Handle> = Vec::new();
+
+    let mut v = start.clone();
+
+    res.push(Handle::pack(start.id, start.etype == ExtremityType::Tail));
+
+    while let Some(neighbors) = edges.get_mut(&v) {
+        neighbors.retain(|(_, w)| *w > 0);
+        if neighbors.is_empty() {
+            edges.remove(&v);
+            break;
+        }
+
+        let mut candidates: Vec<(Extremity, usize)> = neighbors.iter().cloned().collect();
+        candidates.sort();
+        let (u, w) = candidates.swap_remove(choose(res.last().unwrap(), &candidates));
+        neighbors.remove(&(u.clone(), w));
+        if w > 1 {
+            neighbors.insert((u.clone(), w - 1));
+        } else if neighbors.is_empty() {
+            edges.remove(&v);
+        }
+
+        v = Extremity {
+            id: u.id,
+            etype: match u.etype {
+                ExtremityType::Head => ExtremityType::Tail,
+                ExtremityType::Tail => ExtremityType::Head,
+            },
+        };
+        res.push(Handle::pack(v.id, v.etype == ExtremityType::Tail));
+        // stop if start is revisted
+        if &v == start {
+            break;
+        }
+    }
+
+    res
+}
+
+// like extract_random_walk_from_flow, but successors are chosen in a reproducible manner: if
+// haplotype adjacencies are given, the successor that continues the most haplotypes traversing
+// the walk's last adjacency is preferred, followed by any successor supported by a haplotype;
+// remaining ties are resolved by choosing the smallest extremity
+pub fn extract_guided_walk_from_flow(
+    edges: &mut FxHashMap<Extremity, FxHashSet<(Extremity, usize)>>,
+    start: &Extremity,
+    adjs: Option<&AdjacencyMap>,
+) -> Vec<Handle> {
+    // haplotype positions that are consistent with the last adjacency of the walk
+    let mut active: FxHashSet<(usize, usize, bool)> = FxHashSet::default();
+
+    extract_walk_from_flow(edges, start, |cur, candidates| {
+        let adjs = match adjs {
+            None => return 0,
+            Some(adjs) => adjs,
+        };
+        let mut best = 0;
+        let mut best_score = (0, 0);
+        let mut best_active = FxHashSet::default();
+        for (k, (u, _)) in candidates.iter().enumerate() {
+            let e = (*cur, Handle::pack(u.id, u.etype == ExtremityType::Head));
+            let cont = continue_adj(adjs, &active, &e);
+            let score = (cont.len(), adjs.get(&e).map(|x| x.len()).unwrap_or(0));
+            if k == 0 || score > best_score {
+                best = k;
+                best_score = score;
+                best_active = if cont.is_empty() {
+                    adjs.get(&e).cloned().unwrap_or_default()
+                } else {
+                    cont
+                };
+            }
+        }
+        active = best_active;
+        best
+    })
+}
+
+// like extract_random_walk_from_flow, but successors are drawn uniformly at random
+pub fn extract_sampled_walk_from_flow<R: Rng>(
+    edges: &mut FxHashMap<Extremity, FxHashSet<(Extremity, usize)>>,
+    start: &Extremity,
+    rng: &mut R,
+) -> Vec<Handle> {
+    extract_walk_from_flow(edges, start, |_, candidates| {
+        rng.gen_range(0..candidates.len())
+    })
+}
@@ -30,7 +30,7 @@ pub fn str2ext(m: &str) -> Option<ExtremityType> {
     }
 }
 
-#[derive(Clone, Debug, PartialEq, Hash)]
+#[derive(Clone, Debug, PartialOrd, Ord, PartialEq, Hash)]
 pub struct Extremity {
     pub id: usize,
     pub etype: ExtremityType,
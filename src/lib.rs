@@ -1,12 +1,21 @@
+pub mod bubble;
+pub mod compression;
+pub mod dump;
 pub mod flow;
 pub mod graph;
+pub mod names;
+pub mod pansn;
+pub mod reader;
 pub mod sequence;
 
 /* crate use */
 use rustc_hash::FxHashSet;
 
 /* private use */
-pub use crate::{flow::*, graph::*, sequence::*};
+pub use crate::{
+    bubble::*, compression::*, dump::*, flow::*, graph::*, names::*, pansn::*, reader::*,
+    sequence::*,
+};
 
 // copied from da internet
 // split off an arbitrary element from a (non-empty) set
@@ -0,0 +1,72 @@
+/* standard use */
+use std::str::FromStr;
+
+/* crate use */
+use handlegraph::handle::Handle;
+use rustc_hash::FxHashMap;
+
+// translation of segment names into the dense numeric identifiers required by handle graphs and
+// linear programs; the empty table stands for graphs whose segment names are all numeric and
+// serve as identifiers themselves
+#[derive(Clone, Debug, Default)]
+pub struct SegmentNames {
+    ids: FxHashMap<String, usize>,
+    names: Vec<String>,
+}
+
+impl SegmentNames {
+    pub fn is_identity(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    // identifier of given name, assigning the next free identifier (starting at 1) to new names
+    pub fn insert(&mut self, name: &str) -> usize {
+        match self.ids.get(name) {
+            Some(id) => *id,
+            None => {
+                self.names.push(name.to_string());
+                self.ids.insert(name.to_string(), self.names.len());
+                self.names.len()
+            }
+        }
+    }
+
+    pub fn id(&self, name: &str) -> Result<usize, String> {
+        if self.is_identity() {
+            usize::from_str(name).map_err(|_| {
+                format!(
+                    "segment name \"{}\" is not numeric, the graph is required to translate it",
+                    name
+                )
+            })
+        } else {
+            self.ids
+                .get(name)
+                .copied()
+                .ok_or(format!("unknown segment \"{}\"", name))
+        }
+    }
+
+    // identifiers without entry in the table (e.g., nodes added to the graph) are reported as is
+    pub fn name(&self, id: usize) -> String {
+        match id.checked_sub(1).and_then(|i| self.names.get(i)) {
+            Some(name) => name.clone(),
+            None => id.to_string(),
+        }
+    }
+
+    pub fn v2str(&self, v: &Handle) -> String {
+        format!(
+            "{}{}",
+            if v.is_reverse() { '<' } else { '>' },
+            self.name(v.unpack_number() as usize)
+        )
+    }
+
+    pub fn v2seq(&self, v: &[Handle], sep: &str) -> String {
+        v.iter()
+            .map(|x| self.v2str(x))
+            .collect::<Vec<String>>()
+            .join(sep)
+    }
+}
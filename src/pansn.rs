@@ -0,0 +1,90 @@
+/* standard use */
+use std::fmt;
+use std::io::{self, BufRead};
+use std::str::FromStr;
+
+/* crate use */
+use rustc_hash::FxHashSet;
+
+// path name following the PanSN naming convention "sample#haplotype#contig"; names that do not
+// adhere to the convention are taken as sample name as a whole
+#[derive(Clone, Debug, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PanSN {
+    pub sample: String,
+    pub haplotype: Option<usize>,
+    pub contig: Option<String>,
+}
+
+impl fmt::Display for PanSN {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.write_str(&self.sample)?;
+        if let Some(h) = self.haplotype {
+            fmt.write_fmt(format_args!("#{}", h))?;
+        }
+        if let Some(c) = &self.contig {
+            fmt.write_fmt(format_args!("#{}", c))?;
+        }
+        Ok(())
+    }
+}
+
+pub fn parse_pansn(name: &str) -> PanSN {
+    let mut it = name.splitn(3, '#');
+    let sample = it.next().unwrap();
+    match it.next().map(usize::from_str) {
+        Some(Ok(h)) => PanSN {
+            sample: sample.to_string(),
+            haplotype: Some(h),
+            contig: it.next().map(|c| c.to_string()),
+        },
+        _ => PanSN {
+            sample: name.to_string(),
+            haplotype: None,
+            contig: None,
+        },
+    }
+}
+
+// selection of paths by components of their PanSN names; unset criteria match any path
+#[derive(Debug, Default)]
+pub struct PanSNSelection {
+    pub samples: Option<FxHashSet<String>>,
+    pub haplotype: Option<usize>,
+    pub contig: Option<String>,
+}
+
+impl PanSNSelection {
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_none() && self.haplotype.is_none() && self.contig.is_none()
+    }
+
+    pub fn matches(&self, name: &PanSN) -> bool {
+        self.samples
+            .as_ref()
+            .map(|s| s.contains(&name.sample))
+            .unwrap_or(true)
+            && self
+                .haplotype
+                .map(|h| name.haplotype == Some(h))
+                .unwrap_or(true)
+            && self
+                .contig
+                .as_ref()
+                .map(|c| name.contig.as_ref() == Some(c))
+                .unwrap_or(true)
+    }
+}
+
+// sample names, one per line; blank lines and lines starting with "#" are skipped
+pub fn read_sample_list<R: io::Read>(data: io::BufReader<R>) -> Result<FxHashSet<String>, String> {
+    let mut res: FxHashSet<String> = FxHashSet::default();
+
+    for line_op in data.lines() {
+        let line = line_op.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if !line.is_empty() && !line.starts_with('#') {
+            res.insert(line.to_string());
+        }
+    }
+    Ok(res)
+}
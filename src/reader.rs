@@ -0,0 +1,323 @@
+/* standard use */
+use std::io::{self, BufRead};
+use std::str::{self, FromStr};
+
+/* crate use */
+use handlegraph::{
+    handle::{Edge, Handle},
+    handlegraph::*,
+    hashgraph::HashGraph,
+    mutablehandlegraph::AdditiveHandleGraph,
+    pathhandlegraph::embedded_paths::MutableGraphPaths,
+};
+use rustc_hash::FxHashMap;
+
+/* private use */
+use crate::names::SegmentNames;
+use crate::sequence::{parse_cigar, GFAAnnotations};
+
+// parts of a GFA file that are loaded by read_gfa; everything else is skipped while streaming
+// through the file, so that no full copy of the GFA is held in memory. All segments and links are
+// always loaded, since tools rely on the topology of the whole graph (e.g., to identify sources and
+// sinks), so memory grows with the size of the graph, but not with the length of its sequences
+// (unless they are kept) or the number of its paths (beyond those selected)
+#[derive(Default)]
+pub struct GFAReadOptions<'a> {
+    // keep segment sequences; otherwise segments are stored with placeholder sequence "*"
+    pub sequences: bool,
+    // record segment lengths, taken from the sequence or, if omitted, from the LN tag
+    pub lengths: bool,
+    // keep only paths whose names are accepted by the given function; no paths are kept if unset
+    pub paths: Option<&'a dyn Fn(&str) -> bool>,
+    // keep header lines and optional tags of segments, links and (selected) paths
+    pub tags: bool,
+}
+
+pub struct StreamedGFA {
+    pub graph: HashGraph,
+    pub lengths: FxHashMap<usize, usize>,
+    pub names: SegmentNames,
+    pub annotations: GFAAnnotations,
+}
+
+// segment names referred to by a line, in order of occurrence
+fn line_segment_names<'a>(fields: &[&'a [u8]]) -> Vec<&'a [u8]> {
+    match fields[0] {
+        b"S" if fields.len() >= 3 => vec![fields[1]],
+        b"L" if fields.len() >= 5 => vec![fields[1], fields[3]],
+        b"P" if fields.len() >= 3 => fields[2]
+            .split(|&c| c == b',')
+            .filter(|step| !step.is_empty())
+            .map(|step| &step[..step.len() - 1])
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+// segment identifier of a name that has been registered before; without translation, names that
+// are not numeric yield None
+fn segment_id(s: &[u8], names: &SegmentNames) -> Option<usize> {
+    if names.is_identity() {
+        str::from_utf8(s).ok().and_then(|x| usize::from_str(x).ok())
+    } else {
+        names.id(&String::from_utf8_lossy(s)).ok()
+    }
+}
+
+fn parse_orientation(s: &[u8], line_no: usize) -> Result<bool, String> {
+    match s {
+        b"+" => Ok(false),
+        b"-" => Ok(true),
+        _ => Err(format!(
+            "line {}: unknown orientation \"{}\"",
+            line_no,
+            String::from_utf8_lossy(s)
+        )),
+    }
+}
+
+fn parse_path_steps(
+    s: &[u8],
+    names: &SegmentNames,
+    line_no: usize,
+) -> Result<Option<Vec<Handle>>, String> {
+    let mut res: Vec<Handle> = Vec::new();
+    for step in s.split(|&c| c == b',') {
+        if step.is_empty() {
+            return Err(format!("line {}: empty path step", line_no));
+        }
+        let (sid, o) = step.split_at(step.len() - 1);
+        match segment_id(sid, names) {
+            None => return Ok(None),
+            Some(sid) => res.push(Handle::pack(sid, parse_orientation(o, line_no)?)),
+        }
+    }
+    Ok(Some(res))
+}
+
+// optional fields starting at given column, joined by tabs
+fn line_tags(fields: &[&[u8]], start: usize) -> Option<String> {
+    if fields.len() > start {
+        Some(String::from_utf8_lossy(&fields[start..].join(&b'\t')).to_string())
+    } else {
+        None
+    }
+}
+
+fn segment_length(fields: &[&[u8]]) -> usize {
+    if fields[2] != b"*" {
+        return fields[2].len();
+    }
+    fields[3..]
+        .iter()
+        .find(|f| f.starts_with(b"LN:i:"))
+        .and_then(|f| str::from_utf8(&f[5..]).ok())
+        .and_then(|x| usize::from_str(x).ok())
+        .unwrap_or(0)
+}
+
+// reads segments, links and (selected) paths of a GFA1 file in a single pass; links and paths are
+// added to the graph once all segments are known, since GFA does not prescribe an order of lines.
+// Segment names are taken as identifiers unless translate is set, in which case segments are
+// numbered in order of their first occurrence; if a non-numeric name is encountered without
+// translation, None is returned
+fn stream_gfa<R: io::Read>(
+    mut data: io::BufReader<R>,
+    options: &GFAReadOptions,
+    translate: bool,
+) -> Result<Option<StreamedGFA>, String> {
+    let mut graph = HashGraph::new();
+    let mut names = SegmentNames::default();
+    let mut annotations = GFAAnnotations::default();
+    let mut lengths: FxHashMap<usize, usize> = FxHashMap::default();
+    let mut links: Vec<Edge> = Vec::new();
+    let mut paths: Vec<(Vec<u8>, Vec<Handle>)> = Vec::new();
+
+    let mut buf: Vec<u8> = Vec::new();
+    let mut line_no = 0;
+    loop {
+        buf.clear();
+        if data
+            .read_until(b'\n', &mut buf)
+            .map_err(|e| e.to_string())?
+            == 0
+        {
+            break;
+        }
+        line_no += 1;
+        while buf.last() == Some(&b'\n') || buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+        let fields: Vec<&[u8]> = buf.split(|&c| c == b'\t').collect();
+        if translate {
+            for name in line_segment_names(&fields) {
+                names.insert(&String::from_utf8_lossy(name));
+            }
+        }
+        match fields[0] {
+            b"H" if options.tags => {
+                annotations
+                    .header
+                    .push(String::from_utf8_lossy(&buf).to_string());
+            }
+            b"S" if fields.len() >= 3 => {
+                let sid = match segment_id(fields[1], &names) {
+                    Some(x) => x,
+                    None => return Ok(None),
+                };
+                if options.lengths {
+                    lengths.insert(sid, segment_length(&fields));
+                }
+                if let Some(tags) = line_tags(&fields, 3).filter(|_| options.tags) {
+                    annotations.segment_tags.insert(sid, tags);
+                }
+                if options.sequences && !fields[2].is_empty() {
+                    graph.create_handle(fields[2], sid);
+                } else {
+                    graph.create_handle(b"*", sid);
+                }
+            }
+            b"L" if fields.len() >= 5 => {
+                let (u, v) = match (segment_id(fields[1], &names), segment_id(fields[3], &names)) {
+                    (Some(u), Some(v)) => (
+                        Handle::pack(u, parse_orientation(fields[2], line_no)?),
+                        Handle::pack(v, parse_orientation(fields[4], line_no)?),
+                    ),
+                    _ => return Ok(None),
+                };
+                if let Some(cigar) = fields.get(5).map(|x| String::from_utf8_lossy(x)) {
+                    if parse_cigar(&cigar).is_none() {
+                        return Err(format!("line {}: invalid overlap \"{}\"", line_no, cigar));
+                    }
+                    if cigar != "0M" {
+                        annotations.overlaps.insert(Edge(u, v), cigar.to_string());
+                    }
+                }
+                if let Some(tags) = line_tags(&fields, 6).filter(|_| options.tags) {
+                    annotations.link_tags.insert(Edge(u, v), tags);
+                }
+                if graph.has_node(u.id()) && graph.has_node(v.id()) {
+                    graph.create_edge(Edge(u, v));
+                } else {
+                    links.push(Edge(u, v));
+                }
+            }
+            b"P" if fields.len() >= 3 => {
+                if let Some(select) = options.paths {
+                    let name = str::from_utf8(fields[1])
+                        .map_err(|_| format!("line {}: invalid path name", line_no))?;
+                    if select(name) {
+                        match parse_path_steps(fields[2], &names, line_no)? {
+                            Some(steps) => {
+                                if let Some(tags) = line_tags(&fields, 4).filter(|_| options.tags) {
+                                    annotations.path_tags.insert(fields[1].to_vec(), tags);
+                                }
+                                paths.push((fields[1].to_vec(), steps))
+                            }
+                            None => return Ok(None),
+                        }
+                    }
+                }
+            }
+            b"S" | b"L" | b"P" => {
+                return Err(format!(
+                    "line {}: truncated {} line",
+                    line_no,
+                    String::from_utf8_lossy(fields[0])
+                ))
+            }
+            _ => (),
+        }
+    }
+
+    for Edge(u, v) in links {
+        for w in [u, v] {
+            if !graph.has_node(w.id()) {
+                return Err(format!(
+                    "link {}{} refers to unknown segment {}",
+                    names.v2str(&u),
+                    names.v2str(&v),
+                    names.name(w.unpack_number() as usize)
+                ));
+            }
+        }
+        graph.create_edge(Edge(u, v));
+    }
+
+    for (name, steps) in paths {
+        if let Some(w) = steps.iter().find(|w| !graph.has_node(w.id())) {
+            return Err(format!(
+                "path {} refers to unknown segment {}",
+                String::from_utf8_lossy(&name),
+                names.name(w.unpack_number() as usize)
+            ));
+        }
+        let path_id = graph
+            .create_path(&name, false)
+            .ok_or(format!("duplicate path {}", String::from_utf8_lossy(&name)))?;
+        for w in steps {
+            graph.path_append_step(path_id, w);
+        }
+    }
+
+    Ok(Some(StreamedGFA {
+        graph,
+        lengths,
+        names,
+        annotations,
+    }))
+}
+
+// reads a GFA1 file, translating segment names into identifiers if not all of them are numeric;
+// graphs with non-numeric names are read a second time, since this is only known once one of
+// them is encountered
+pub fn read_gfa_file(file: &str, options: &GFAReadOptions) -> Result<StreamedGFA, io::Error> {
+    let to_io_error =
+        |e: String| io::Error::new(io::ErrorKind::InvalidData, format!("{}: {}", file, e));
+    match stream_gfa(crate::open_file(file)?, options, false).map_err(to_io_error)? {
+        Some(res) => Ok(res),
+        None => {
+            log::info!(
+                "graph {} has non-numeric segment names, translating them",
+                file
+            );
+            Ok(stream_gfa(crate::open_file(file)?, options, true)
+                .map_err(to_io_error)?
+                .unwrap())
+        }
+    }
+}
+
+// translation table of the segment names of a GFA1 file, which is consistent with the one of
+// read_gfa_file
+pub fn read_segment_names(file: &str) -> Result<SegmentNames, io::Error> {
+    let mut data = crate::open_file(file)?;
+    let mut names = SegmentNames::default();
+    let mut numeric = true;
+
+    let mut buf: Vec<u8> = Vec::new();
+    loop {
+        buf.clear();
+        if data.read_until(b'\n', &mut buf)? == 0 {
+            break;
+        }
+        while buf.last() == Some(&b'\n') || buf.last() == Some(&b'\r') {
+            buf.pop();
+        }
+        let fields: Vec<&[u8]> = buf.split(|&c| c == b'\t').collect();
+        for name in line_segment_names(&fields) {
+            numeric &= segment_id(name, &SegmentNames::default()).is_some();
+            names.insert(&String::from_utf8_lossy(name));
+        }
+    }
+
+    if numeric {
+        Ok(SegmentNames::default())
+    } else {
+        log::info!(
+            "graph {} has non-numeric segment names, translating them",
+            file
+        );
+        Ok(names)
+    }
+}
@@ -1,5 +1,7 @@
 /* standard use */
-use std::io::{self, Write};
+use std::cmp::min;
+use std::io::{self, BufRead, Write};
+use std::iter::FromIterator;
 use std::str;
 use std::str::FromStr;
 
@@ -10,8 +12,13 @@ use handlegraph::{
     hashgraph::HashGraph,
     pathhandlegraph::PathId,
 };
+use itertools::Itertools;
 use log::info;
-use rustc_hash::FxHashSet;
+use rand::distributions::{Distribution, Uniform};
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/* private use */
+use crate::names::SegmentNames;
 
 pub fn v2extstr(v: &Handle) -> String {
     format!(
@@ -37,8 +44,12 @@ pub fn hv2seq(v: &FxHashSet<Handle>, sep: &str) -> String {
     v.iter().map(v2str).collect::<Vec<String>>().join(sep)
 }
 
-pub fn push_walk_step(walk: &mut Vec<Handle>, step: &Vec<u8>) -> Result<(), String> {
-    let sid = usize::from_str(str::from_utf8(&step[1..]).unwrap()).unwrap();
+pub fn push_walk_step(
+    walk: &mut Vec<Handle>,
+    step: &Vec<u8>,
+    names: &SegmentNames,
+) -> Result<(), String> {
+    let sid = names.id(str::from_utf8(&step[1..]).unwrap())?;
     let is_rev = match step[0] {
         b'>' => Ok(false),
         b'<' => Ok(true),
@@ -64,85 +75,497 @@ pub fn reverse_seq(seq: &Vec<Handle>) -> Vec<Handle> {
     rev
 }
 
-pub fn parse_walk(line: &String) -> Result<Vec<Handle>, String> {
+pub fn parse_walk(line: &String, names: &SegmentNames) -> Result<Vec<Handle>, String> {
     let line = line[line.find('\t').unwrap() + 1..].as_bytes().to_vec();
 
     let mut walk: Vec<Handle> = Vec::new();
     let mut step: Vec<u8> = Vec::new();
     for c in line {
         if (c == b'>' || c == b'<') && !step.is_empty() {
-            push_walk_step(&mut walk, &step)?;
+            push_walk_step(&mut walk, &step, names)?;
+            step.clear();
+        }
+        step.push(c);
+    }
+    if !step.is_empty() {
+        push_walk_step(&mut walk, &step, names)?;
+    }
+    Ok(walk)
+}
+
+// parses a walk like parse_walk, but drops steps of segments that are unknown to the name table,
+// such as nodes added by subgr
+pub fn parse_walk_lenient(line: &str, names: &SegmentNames) -> Result<Vec<Handle>, String> {
+    let line = &line.as_bytes()[line.find('\t').unwrap() + 1..];
+
+    let mut walk: Vec<Handle> = Vec::new();
+    let mut push = |step: &Vec<u8>| match names.id(str::from_utf8(&step[1..]).unwrap()) {
+        Ok(_) => push_walk_step(&mut walk, step, names),
+        Err(_) => Ok(()),
+    };
+    let mut step: Vec<u8> = Vec::new();
+    for &c in line {
+        if (c == b'>' || c == b'<') && !step.is_empty() {
+            push(&step)?;
             step.clear();
         }
         step.push(c);
     }
     if !step.is_empty() {
-        push_walk_step(&mut walk, &step)?;
+        push(&step)?;
     }
     Ok(walk)
 }
 
+// reads a list of node ids, given either individually or as inclusive ranges "a-b", one per line
+pub fn read_node_ranges<R: io::Read>(
+    data: io::BufReader<R>,
+    names: &SegmentNames,
+) -> Result<FxHashSet<u64>, String> {
+    let mut res: FxHashSet<u64> = FxHashSet::default();
+
+    for line_op in data.lines() {
+        let line = line_op.map_err(|e| e.to_string())?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        let parse_id = |x: &str| names.id(x.trim()).map(|x| x as u64);
+        // segment names may themselves contain "-"
+        match (parse_id(line), line.split_once('-')) {
+            (Ok(x), _) => {
+                res.insert(x);
+            }
+            (Err(_), Some((a, b))) => {
+                res.extend(parse_id(a)?..=parse_id(b)?);
+            }
+            (Err(e), None) => return Err(e),
+        }
+    }
+    Ok(res)
+}
+
+// walks of haplotypes or founders, one tab-separated pair of name and walk per line
+pub fn read_walks<R: io::Read>(
+    data: io::BufReader<R>,
+    names: &SegmentNames,
+) -> Result<Vec<(String, Vec<Handle>)>, String> {
+    let mut res: Vec<(String, Vec<Handle>)> = Vec::new();
+
+    for line_op in data.lines() {
+        let line = line_op.map_err(|e| e.to_string())?;
+        if line.is_empty() {
+            continue;
+        }
+        let name = match line.find('\t') {
+            Some(i) => line[..i].to_string(),
+            None => return Err(format!("missing walk in line \"{}\"", line)),
+        };
+        res.push((name, parse_walk(&line, names)?));
+    }
+
+    Ok(res)
+}
+
+// haplotypes may be given in fragments, either as several lines of the same name or as walks
+// interrupted by gaps ("*"); each non-empty fragment is reported as a walk on its own
+pub fn read_haplotype_fragments<R: io::Read>(
+    data: io::BufReader<R>,
+    names: &SegmentNames,
+) -> Result<Vec<(String, Vec<Handle>)>, String> {
+    Ok(read_weighted_haplotype_fragments(data, names)?
+        .into_iter()
+        .map(|(name, walk, _)| (name, walk))
+        .collect())
+}
+
+// like read_haplotype_fragments, but also reports the weight of each fragment (e.g., the number of
+// collapsed identical haplotypes), given in an optional third column either as plain number or as
+// tag "WT:i:<int>" or "WT:f:<float>"; the default weight is 1
+pub fn read_weighted_haplotype_fragments<R: io::Read>(
+    data: io::BufReader<R>,
+    names: &SegmentNames,
+) -> Result<Vec<(String, Vec<Handle>, f64)>, String> {
+    let mut res: Vec<(String, Vec<Handle>, f64)> = Vec::new();
+
+    for line_op in data.lines() {
+        let line = line_op.map_err(|e| e.to_string())?;
+        let mut it = line.split('\t');
+        let name = it.next().unwrap();
+        let walk = it
+            .next()
+            .ok_or(format!("missing walk of haplotype {}", name))?;
+        let weight = match it.next() {
+            None => 1.0,
+            Some(w) => {
+                let x = w
+                    .strip_prefix("WT:i:")
+                    .or_else(|| w.strip_prefix("WT:f:"))
+                    .unwrap_or(w);
+                match f64::from_str(x) {
+                    Ok(x) if x > 0.0 => x,
+                    _ => return Err(format!("invalid weight \"{}\" of haplotype {}", w, name)),
+                }
+            }
+        };
+        for fragment in walk.split('*') {
+            let walk = parse_walk(&format!("\t{}", fragment), names)?;
+            if !walk.is_empty() {
+                res.push((name.to_string(), walk, weight));
+            }
+        }
+    }
+
+    Ok(res)
+}
+
+// maps each adjacency to the haplotype positions at which it occurs, in the form (haplotype
+// index, position, reverse orientation)
+pub type AdjacencyMap = FxHashMap<(Handle, Handle), FxHashSet<(usize, usize, bool)>>;
+
+pub fn haplotype_to_adj_map(haps: &[(String, Vec<Handle>)]) -> AdjacencyMap {
+    let mut res: AdjacencyMap = FxHashMap::default();
+    for (x, (_, s)) in haps.iter().enumerate() {
+        let n = s.len() - 1;
+        s.iter()
+            .tuple_windows()
+            .enumerate()
+            .for_each(|(i, (u, v))| {
+                res.entry((v.flip(), u.flip()))
+                    .or_default()
+                    .insert((x, n - i, true));
+                res.entry((*u, *v)).or_default().insert((x, i, false));
+            })
+    }
+    res
+}
+
+// positions at which haplotypes traverse adjacency e directly after adjacency at positions pos
+pub fn continue_adj(
+    adjs: &AdjacencyMap,
+    pos: &FxHashSet<(usize, usize, bool)>,
+    e: &(Handle, Handle),
+) -> FxHashSet<(usize, usize, bool)> {
+    match adjs.get(e) {
+        None => FxHashSet::default(),
+        Some(next) => pos
+            .iter()
+            .map(|(x, i, o)| (*x, i + 1, *o))
+            .filter(|p| next.contains(p))
+            .collect(),
+    }
+}
+
+// estimates the number of recombinations needed to color seq with haplotypes by repeatedly
+// assigning random colors, keeping a color for as long as its haplotype continues; if a mismatch
+// penalty is given, adjacencies not traversed by any haplotype are charged with the penalty and
+// interrupt the coloring, otherwise they render seq uncolorable
+pub fn color_and_count(
+    seq: &[Handle],
+    adjs: &AdjacencyMap,
+    repeats: usize,
+    penalty: Option<usize>,
+) -> usize {
+    let mut rng = rand::thread_rng();
+
+    // construct data structure for efficient access for random selection
+    let adjs_vec: FxHashMap<_, (Vec<_>, Uniform<_>)> =
+        FxHashMap::from_iter(adjs.iter().map(|((u, v), s)| {
+            (
+                (*u, *v),
+                (s.iter().cloned().collect(), Uniform::from(0..s.len())),
+            )
+        }));
+
+    let mut cur_min = usize::MAX;
+    for _ in 0..repeats {
+        let mut c = 0;
+        let mut cur: Option<(usize, usize, bool)> = None;
+        for (&u, &v) in seq.iter().tuple_windows() {
+            let (vs, r) = match (adjs_vec.get(&(u, v)), penalty) {
+                (Some(x), _) => x,
+                (None, Some(p)) => {
+                    c += p;
+                    cur = None;
+                    continue;
+                }
+                (None, None) => panic!(
+                    "oops, adjacency {}{} not contained in map, string is not colorable!",
+                    v2str(&u),
+                    v2str(&v)
+                ),
+            };
+            cur = Some(match cur {
+                None => vs[r.sample(&mut rng)],
+                Some((x, i, o)) => {
+                    if vs.contains(&(x, i + 1, o)) {
+                        (x, i + 1, o)
+                    } else {
+                        c += 1;
+                        vs[r.sample(&mut rng)]
+                    }
+                }
+            });
+        }
+        cur_min = min(cur_min, c);
+    }
+    cur_min
+}
+
+// exact and deterministic counterpart of color_and_count: the minimum number of recombinations
+// needed to color seq with haplotypes, where a color continues for free as long as its haplotype
+// does; adjacencies not traversed by any haplotype are charged as in color_and_count
+pub fn count_recombinations(seq: &[Handle], adjs: &AdjacencyMap, penalty: Option<usize>) -> usize {
+    // minimum cost of coloring seq up to the current adjacency with each of its occurrences, and
+    // cost up to the last interruption of the coloring
+    let mut cur: FxHashMap<(usize, usize, bool), usize> = FxHashMap::default();
+    let mut base = 0;
+    for (&u, &v) in seq.iter().tuple_windows() {
+        let best = cur.values().min().copied();
+        match (adjs.get(&(u, v)), penalty) {
+            (Some(vs), _) => {
+                cur = vs
+                    .iter()
+                    .map(|&(x, i, o)| {
+                        let c = match best {
+                            None => base,
+                            Some(b) => i
+                                .checked_sub(1)
+                                .and_then(|j| cur.get(&(x, j, o)))
+                                .map_or(b + 1, |&c| min(c, b + 1)),
+                        };
+                        ((x, i, o), c)
+                    })
+                    .collect();
+            }
+            (None, Some(p)) => {
+                base = best.unwrap_or(base) + p;
+                cur.clear();
+            }
+            (None, None) => panic!(
+                "oops, adjacency {}{} not contained in map, string is not colorable!",
+                v2str(&u),
+                v2str(&v)
+            ),
+        }
+    }
+    cur.values().min().copied().unwrap_or(base)
+}
+
 pub fn write_founders<W: io::Write>(
     f: &Vec<Vec<Handle>>,
+    names: &SegmentNames,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     log::info!("writing founder sequences to file");
     f.iter()
         .enumerate()
-        .try_for_each(|(i, c)| writeln!(out, "founder_seq{}\t{}", i + 1, v2seq(c, "")))
+        .try_for_each(|(i, c)| writeln!(out, "founder_seq{}\t{}", i + 1, names.v2seq(c, "")))
+}
+
+// overlaps (CIGAR strings) of links other than the default "0M", keyed by links in the orientation
+// given in the GFA file
+pub type LinkOverlaps = FxHashMap<Edge, String>;
+
+pub fn parse_cigar(cigar: &str) -> Option<Vec<(usize, char)>> {
+    if cigar == "*" {
+        return Some(Vec::new());
+    }
+    let mut res: Vec<(usize, char)> = Vec::new();
+    let mut n = String::new();
+    for c in cigar.chars() {
+        if c.is_ascii_digit() {
+            n.push(c);
+        } else if "MIDNSHP=X".contains(c) && !n.is_empty() {
+            res.push((usize::from_str(&n).ok()?, c));
+            n.clear();
+        } else {
+            return None;
+        }
+    }
+    if n.is_empty() {
+        Some(res)
+    } else {
+        None
+    }
+}
+
+// CIGAR of a link traversed in opposite direction, in which insertions become deletions and vice
+// versa
+pub fn reverse_cigar(cigar: &str) -> String {
+    match parse_cigar(cigar) {
+        Some(ops) if !ops.is_empty() => ops
+            .iter()
+            .rev()
+            .map(|(n, op)| {
+                format!(
+                    "{}{}",
+                    n,
+                    match op {
+                        'I' => 'D',
+                        'D' => 'I',
+                        x => *x,
+                    }
+                )
+            })
+            .collect(),
+        _ => cigar.to_string(),
+    }
+}
+
+// overlap of the link from u to v, which may be given in either orientation
+pub fn link_overlap(overlaps: &LinkOverlaps, u: &Handle, v: &Handle) -> String {
+    match overlaps.get(&Edge(*u, *v)) {
+        Some(c) => c.clone(),
+        None => match overlaps.get(&Edge(v.flip(), u.flip())) {
+            Some(c) => reverse_cigar(c),
+            None => String::from("0M"),
+        },
+    }
+}
+
+// number of bases at the beginning of the second segment of a link that are covered by its
+// overlap
+pub fn overlap_length(cigar: &str) -> usize {
+    parse_cigar(cigar)
+        .unwrap_or_default()
+        .iter()
+        .filter(|(_, op)| "MIS=X".contains(*op))
+        .map(|(n, _)| n)
+        .sum()
 }
 
-pub fn write_gfa_header<W: io::Write>(out: &mut io::BufWriter<W>) -> Result<(), io::Error> {
-    writeln!(out, "H\tVN:Z:1.0")
+// start positions of the steps of a walk, followed by the walk's total length; overlaps exceeding
+// the length of a segment (e.g., one without sequence or LN tag) are truncated
+pub fn step_offsets(
+    walk: &[Handle],
+    lengths: &FxHashMap<usize, usize>,
+    overlaps: &LinkOverlaps,
+) -> Vec<usize> {
+    let mut res = vec![0];
+    for (k, v) in walk.iter().enumerate() {
+        let mut x = res[k] + lengths.get(&(v.unpack_number() as usize)).unwrap_or(&0);
+        if k + 1 < walk.len() {
+            let overlap = overlap_length(&link_overlap(overlaps, v, &walk[k + 1]));
+            x = res[k].max(x.saturating_sub(overlap));
+        }
+        res.push(x);
+    }
+    res
+}
+
+// sequence spelled by a walk, in which the overlap of each traversed link is only spelled once
+pub fn spell_walk(graph: &HashGraph, walk: &[Handle], overlaps: &LinkOverlaps) -> Vec<u8> {
+    let mut res: Vec<u8> = Vec::new();
+    for (i, v) in walk.iter().enumerate() {
+        let seq = graph.sequence_vec(*v);
+        let skip = if i == 0 {
+            0
+        } else {
+            overlap_length(&link_overlap(overlaps, &walk[i - 1], v))
+        };
+        res.extend_from_slice(&seq[min(skip, seq.len())..]);
+    }
+    res
+}
+
+// parts of a GFA file that have no counterpart in the handle graph, but are carried through to
+// GFA output; tags are kept verbatim as tab-separated string
+#[derive(Clone, Debug, Default)]
+pub struct GFAAnnotations {
+    pub header: Vec<String>,
+    pub overlaps: LinkOverlaps,
+    pub segment_tags: FxHashMap<usize, String>,
+    pub link_tags: FxHashMap<Edge, String>,
+    pub path_tags: FxHashMap<Vec<u8>, String>,
+}
+
+impl GFAAnnotations {
+    // tags of the link from u to v, which may be given in either orientation
+    pub fn link_tags(&self, u: &Handle, v: &Handle) -> Option<&String> {
+        self.link_tags
+            .get(&Edge(*u, *v))
+            .or_else(|| self.link_tags.get(&Edge(v.flip(), u.flip())))
+    }
+}
+
+fn tag_suffix(tags: Option<&String>) -> String {
+    match tags {
+        Some(t) => format!("\t{}", t),
+        None => String::new(),
+    }
+}
+
+pub fn write_gfa_header<W: io::Write>(
+    annotations: &GFAAnnotations,
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
+    if annotations.header.is_empty() {
+        return writeln!(out, "H\tVN:Z:1.0");
+    }
+    for h in annotations.header.iter() {
+        writeln!(out, "{}", h)?;
+    }
+    Ok(())
 }
 
 pub fn write_gfa_segment<W: io::Write>(
     v: &Handle,
     g: &HashGraph,
+    names: &SegmentNames,
+    annotations: &GFAAnnotations,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     writeln!(
         out,
-        "S\t{}\t{}",
-        v.unpack_number(),
-        String::from_utf8(g.sequence_vec(*v)).unwrap()
+        "S\t{}\t{}{}",
+        names.name(v.unpack_number() as usize),
+        String::from_utf8(g.sequence_vec(*v)).unwrap(),
+        tag_suffix(annotations.segment_tags.get(&(v.unpack_number() as usize)))
     )
 }
 
 pub fn write_gfa_link<W: io::Write>(
     u: &Handle,
     v: &Handle,
+    names: &SegmentNames,
+    annotations: &GFAAnnotations,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     writeln!(
         out,
-        "L\t{}\t{}\t{}\t{}\t0M",
-        u.unpack_number(),
+        "L\t{}\t{}\t{}\t{}\t{}{}",
+        names.name(u.unpack_number() as usize),
         if u.is_reverse() { '-' } else { '+' },
-        v.unpack_number(),
-        if v.is_reverse() { '-' } else { '+' }
+        names.name(v.unpack_number() as usize),
+        if v.is_reverse() { '-' } else { '+' },
+        link_overlap(&annotations.overlaps, u, v),
+        tag_suffix(annotations.link_tags(u, v))
     )
 }
 
 pub fn write_gfa_path<W: io::Write>(
     id: &PathId,
     g: &HashGraph,
+    names: &SegmentNames,
+    annotations: &GFAAnnotations,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     let p = g.get_path(id).unwrap();
     writeln!(
         out,
-        "P\t{}\t{}\t*",
+        "P\t{}\t{}\t*{}",
         str::from_utf8(&p.name).unwrap(),
         p.nodes
             .iter()
             .map(|v| format!(
                 "{}{}",
-                v.unpack_number(),
+                names.name(v.unpack_number() as usize),
                 if v.is_reverse() { '-' } else { '+' }
             ))
             .collect::<Vec<String>>()
-            .join(",")
+            .join(","),
+        tag_suffix(annotations.path_tags.get(&p.name))
     )
 }
 
@@ -151,12 +574,14 @@ pub fn write_subset_gfa<W: io::Write>(
     subgraph_nodes: &FxHashSet<Handle>,
     subgraph_edges: &FxHashSet<Edge>,
     paths: &Vec<PathId>,
+    names: &SegmentNames,
+    annotations: &GFAAnnotations,
     out: &mut io::BufWriter<W>,
 ) -> Result<(), io::Error> {
     info!("writing subset gfa file");
-    write_gfa_header(out)?;
+    write_gfa_header(annotations, out)?;
     for v in subgraph_nodes.iter() {
-        write_gfa_segment(&v, &graph, out)?;
+        write_gfa_segment(&v, &graph, names, annotations, out)?;
     }
     for Edge(mut u, mut v) in graph.edges() {
         if subgraph_edges.contains(&Edge(u, v)) {
@@ -165,26 +590,31 @@ pub fn write_subset_gfa<W: io::Write>(
                 u = v.flip();
                 v = w;
             }
-            write_gfa_link(&u, &v, out)?;
+            write_gfa_link(&u, &v, names, annotations, out)?;
         }
     }
     for path_id in paths.iter() {
-        write_gfa_path(&path_id, &graph, out)?;
+        write_gfa_path(&path_id, &graph, names, annotations, out)?;
     }
     Ok(())
 }
 
-pub fn write_gfa<W: io::Write>(g: &HashGraph, out: &mut io::BufWriter<W>) -> Result<(), io::Error> {
+pub fn write_gfa<W: io::Write>(
+    g: &HashGraph,
+    names: &SegmentNames,
+    annotations: &GFAAnnotations,
+    out: &mut io::BufWriter<W>,
+) -> Result<(), io::Error> {
     info!("writing gfa file");
-    write_gfa_header(out)?;
+    write_gfa_header(annotations, out)?;
     for v in g.handles() {
-        write_gfa_segment(&v, &g, out)?;
+        write_gfa_segment(&v, &g, names, annotations, out)?;
     }
     for Edge(u, v) in g.edges() {
-        write_gfa_link(&u, &v, out)?;
+        write_gfa_link(&u, &v, names, annotations, out)?;
     }
     for p in g.paths.iter() {
-        write_gfa_path(p.0, &g, out)?;
+        write_gfa_path(p.0, &g, names, annotations, out)?;
     }
     Ok(())
 }